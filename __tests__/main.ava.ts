@@ -15,17 +15,22 @@ test.beforeEach(async (t) => {
     // Prepare sandbox for tests, create accounts, deploy contracts, etc.
     const root = worker.rootAccount;
 
-    // Deploy the linkdrop contract and initialize it
+    // Deploy the linkdrop contract and initialize it with an owner other than the contract account,
+    // which drop keys act as
+    const owner = await root.createSubAccount('owner');
     await root.deploy(`./res/linkdrop.wasm`);
-    await root.call(root, 'new', {});
+    await root.call(root, 'new', { owner_id: owner.accountId });
 
     // // Test users
     const creator = await root.createSubAccount('creator');
     const claimer = await root.createSubAccount('claimer');
 
+    // Allow the creator to call `create_account_advanced`
+    await owner.call(root, 'add_admin', { account_id: creator.accountId });
+
     // Save state for test runs
     t.context.worker = worker;
-    t.context.accounts = { root, creator, claimer };
+    t.context.accounts = { root, owner, creator, claimer };
 });
 
 // If the environment is reused, use test.after to replace test.afterEach
@@ -180,10 +185,10 @@ test('Add 2 types of access keys with same public key', async t => {
         }
     );
 
-    // Check for any failures (should be 1 due to adding a key that already exists)
+    // Check for any failures (should be 1, rejected before the account creation is attempted)
     const errors = displayFailureLog(res);
     t.is(errors.length, 1);
-    t.is(errors[0].hasOwnProperty('AddKeyAlreadyExists'), true);
+    t.true(errors[0].FunctionCallError.ExecutionError.includes('Duplicate public key across access keys'));
 
     // The account was not created
     const doesNewAccountExistNow = await newAccount.exists();
@@ -194,3 +199,422 @@ test('Add 2 types of access keys with same public key', async t => {
     t.true(newCreatorBalance.available.lte(creatorBalance.available));
     t.true(newCreatorBalance.available.gte(creatorBalance.available.sub(NEAR.parse("0.01"))));
 });
+
+test('Claim into a new account with a contract deployed', async t => {
+    const { root, creator } = t.context.accounts;
+    const { keys, publicKeys } = await generateKeyPairs(2);
+
+    // Create the linkdrop
+    await creator.call(
+        root,
+        'send',
+        { public_key: publicKeys[0] },
+        { attachedDeposit: NEAR.parse("10 N").toString() }
+    );
+
+    const newAccount = await root.getAccount(`claimed.${root.accountId}`);
+    const doesNewAccountExist = await newAccount.exists();
+    t.is(doesNewAccountExist, false);
+
+    // Get the bytes of a contract, base64 encoded
+    const contractBytes = readFileSync('./__tests__/ext-wasm/nft-tutorial.wasm').toString('base64');
+
+    // Claim the drop with the linkdrop key
+    await root.setKey(keys[0]);
+    const res = await root.callRaw(
+        root,
+        'create_account_and_claim',
+        {
+            new_account_id: newAccount.accountId,
+            new_public_key: publicKeys[1],
+            contract_bytes: contractBytes,
+        },
+        { gas: "300000000000000" }
+    );
+
+    // There were no failures
+    const errors = displayFailureLog(res);
+    t.is(errors.length, 0);
+
+    // The new account exists with the claimer's key and the contract
+    t.is(await newAccount.exists(), true);
+    const newAccountKeys = await root.viewAccessKeys(newAccount.accountId);
+    t.is(newAccountKeys.keys.length, 1);
+    t.is(newAccountKeys.keys[0].public_key, publicKeys[1]);
+
+    // The deployed contract works
+    await creator.call(newAccount, 'new_default_meta', { owner_id: creator.accountId });
+    const meta: any = await newAccount.view('nft_metadata', {});
+    t.is(meta.spec, 'nft-1.0.0');
+});
+
+test('Claim into an existing contract account and call a method on it', async t => {
+    const { root, creator, claimer } = t.context.accounts;
+    const { keys, publicKeys } = await generateKeyPairs(1);
+
+    // The claimer already has a contract deployed
+    await claimer.deploy('./__tests__/ext-wasm/nft-tutorial.wasm');
+
+    // Create the linkdrop
+    await creator.call(
+        root,
+        'send',
+        { public_key: publicKeys[0] },
+        { attachedDeposit: NEAR.parse("5 N").toString() }
+    );
+    const claimerBalance = await claimer.balance();
+
+    // Claim the drop and initialize the claimer's contract in the same flow
+    await root.setKey(keys[0]);
+    const res = await root.callRaw(
+        root,
+        'claim_and_call',
+        {
+            account_id: claimer.accountId,
+            method_name: 'new_default_meta',
+            args: Buffer.from(JSON.stringify({ owner_id: claimer.accountId })).toString('base64'),
+            gas: "50000000000000",
+        },
+        { gas: "300000000000000" }
+    );
+
+    // There were no failures
+    const errors = displayFailureLog(res);
+    t.is(errors.length, 0);
+
+    // The claimer received the drop
+    const newClaimerBalance = await claimer.balance();
+    t.true(newClaimerBalance.total.gt(claimerBalance.total.add(NEAR.parse("3.9 N"))));
+
+    // The method was called on the claimer's contract
+    const meta: any = await claimer.view('nft_metadata', {});
+    t.is(meta.spec, 'nft-1.0.0');
+});
+
+test('Claim an NFT drop', async t => {
+    const { root, creator, claimer } = t.context.accounts;
+    const { keys, publicKeys } = await generateKeyPairs(1);
+
+    // Deploy an NFT contract and mint a token to the creator
+    const nft = await root.createSubAccount('nft');
+    await nft.deploy('./__tests__/ext-wasm/nft-tutorial.wasm');
+    await nft.call(nft, 'new_default_meta', { owner_id: nft.accountId });
+    await creator.call(
+        nft,
+        'nft_mint',
+        {
+            token_id: 'token-1',
+            metadata: { title: 'Linkdrop NFT' },
+            receiver_id: creator.accountId,
+        },
+        { attachedDeposit: NEAR.parse("0.1 N").toString() }
+    );
+
+    // Create the linkdrop and attach the NFT to it
+    await creator.call(
+        root,
+        'send',
+        { public_key: publicKeys[0] },
+        { attachedDeposit: NEAR.parse("2 N").toString() }
+    );
+    await creator.call(
+        root,
+        'expect_nft',
+        { public_key: publicKeys[0], contract_id: nft.accountId, token_id: 'token-1' }
+    );
+    await creator.call(
+        nft,
+        'nft_transfer_call',
+        { receiver_id: root.accountId, token_id: 'token-1', msg: publicKeys[0] },
+        { attachedDeposit: "1", gas: "300000000000000" }
+    );
+    let token: any = await nft.view('nft_token', { token_id: 'token-1' });
+    t.is(token.owner_id, root.accountId);
+
+    // Claim the drop
+    await root.setKey(keys[0]);
+    const res = await root.callRaw(
+        root,
+        'claim',
+        { account_id: claimer.accountId },
+        { gas: "300000000000000" }
+    );
+
+    // There were no failures
+    const errors = displayFailureLog(res);
+    t.is(errors.length, 0);
+
+    // The claimer received the NFT
+    token = await nft.view('nft_token', { token_id: 'token-1' });
+    t.is(token.owner_id, claimer.accountId);
+});
+
+test('Owner recovers a stuck NFT drop', async t => {
+    const { root, owner, creator, claimer } = t.context.accounts;
+    const { publicKeys } = await generateKeyPairs(1);
+
+    // Deploy an NFT contract and mint a token to the creator
+    const nft = await root.createSubAccount('nft');
+    await nft.deploy('./__tests__/ext-wasm/nft-tutorial.wasm');
+    await nft.call(nft, 'new_default_meta', { owner_id: nft.accountId });
+    await creator.call(
+        nft,
+        'nft_mint',
+        {
+            token_id: 'token-1',
+            metadata: { title: 'Linkdrop NFT' },
+            receiver_id: creator.accountId,
+        },
+        { attachedDeposit: NEAR.parse("0.1 N").toString() }
+    );
+
+    // Create the linkdrop and attach the NFT to it
+    await creator.call(
+        root,
+        'send',
+        { public_key: publicKeys[0] },
+        { attachedDeposit: NEAR.parse("2 N").toString() }
+    );
+    await creator.call(
+        root,
+        'expect_nft',
+        { public_key: publicKeys[0], contract_id: nft.accountId, token_id: 'token-1' }
+    );
+    await creator.call(
+        nft,
+        'nft_transfer_call',
+        { receiver_id: root.accountId, token_id: 'token-1', msg: publicKeys[0] },
+        { attachedDeposit: "1", gas: "300000000000000" }
+    );
+
+    // The key was lost: the owner sends the NFT to an account of their choice
+    const res = await owner.callRaw(
+        root,
+        'withdraw_nft',
+        { public_key: publicKeys[0], to: claimer.accountId },
+        { gas: "300000000000000" }
+    );
+
+    // There were no failures
+    const errors = displayFailureLog(res);
+    t.is(errors.length, 0);
+
+    // The NFT was recovered and the drop is gone
+    const token: any = await nft.view('nft_token', { token_id: 'token-1' });
+    t.is(token.owner_id, claimer.accountId);
+    const keys = await root.viewAccessKeys(root.accountId);
+    t.false(keys.keys.some((key: any) => key.public_key === publicKeys[0]));
+});
+
+test('Claim returns the claimed amount', async t => {
+    const { root, creator, claimer } = t.context.accounts;
+    const { keys, publicKeys } = await generateKeyPairs(1);
+
+    // Create the linkdrop
+    await creator.call(
+        root,
+        'send',
+        { public_key: publicKeys[0] },
+        { attachedDeposit: NEAR.parse("5 N").toString() }
+    );
+    const keyBalance: string = await root.view('get_key_balance', { key: publicKeys[0] });
+
+    // Claim the drop and read the amount from the transaction result
+    await root.setKey(keys[0]);
+    const res = await root.callRaw(
+        root,
+        'claim_with_receipt',
+        { account_id: claimer.accountId },
+        { gas: "300000000000000" }
+    );
+
+    // There were no failures and the whole drop balance was claimed
+    const errors = displayFailureLog(res);
+    t.is(errors.length, 0);
+    t.is(res.parseResult<string>(), keyBalance);
+    t.is(keyBalance, NEAR.parse("4 N").toString());
+});
+
+test('Ensure account and claim creates a missing account', async t => {
+    const { root, creator } = t.context.accounts;
+    const { keys, publicKeys } = await generateKeyPairs(2);
+
+    // Create the linkdrop
+    await creator.call(
+        root,
+        'send',
+        { public_key: publicKeys[0] },
+        { attachedDeposit: NEAR.parse("5 N").toString() }
+    );
+
+    const newAccount = await root.getAccount(`ensured.${root.accountId}`);
+    t.is(await newAccount.exists(), false);
+
+    // Claim without knowing whether the account exists
+    await root.setKey(keys[0]);
+    const res = await root.callRaw(
+        root,
+        'ensure_account_and_claim',
+        { account_id: newAccount.accountId, public_key: publicKeys[1] },
+        { gas: "300000000000000" }
+    );
+
+    // There were no failures
+    const errors = displayFailureLog(res);
+    t.is(errors.length, 0);
+
+    // The account was created with the key and the drop balance
+    t.is(await newAccount.exists(), true);
+    const newAccountKeys = await root.viewAccessKeys(newAccount.accountId);
+    t.is(newAccountKeys.keys[0].public_key, publicKeys[1]);
+    const balance = await newAccount.balance();
+    t.true(balance.total.gte(NEAR.parse("3.9 N")));
+});
+
+test('Ensure account and claim tops up an existing account', async t => {
+    const { root, creator, claimer } = t.context.accounts;
+    const { keys, publicKeys } = await generateKeyPairs(2);
+
+    // Create the linkdrop
+    await creator.call(
+        root,
+        'send',
+        { public_key: publicKeys[0] },
+        { attachedDeposit: NEAR.parse("5 N").toString() }
+    );
+    const claimerBalance = await claimer.balance();
+
+    // Claim into an account that already exists
+    await root.setKey(keys[0]);
+    const res = await root.callRaw(
+        root,
+        'ensure_account_and_claim',
+        { account_id: claimer.accountId, public_key: publicKeys[1] },
+        { gas: "300000000000000" }
+    );
+
+    // The only failure is the attempt to create the existing account
+    const errors = displayFailureLog(res);
+    t.is(errors.length, 1);
+    t.is(errors[0].hasOwnProperty('AccountAlreadyExists'), true);
+
+    // The claimer received the drop and the drop is gone
+    const newClaimerBalance = await claimer.balance();
+    t.true(newClaimerBalance.total.gt(claimerBalance.total.add(NEAR.parse("3.9 N"))));
+    const keysOnLinkdrop = await root.viewAccessKeys(root.accountId);
+    t.false(keysOnLinkdrop.keys.some((key: any) => key.public_key === publicKeys[0]));
+});
+
+test('Smart claim creates a new account', async t => {
+    const { root, creator } = t.context.accounts;
+    const { keys, publicKeys } = await generateKeyPairs(2);
+
+    // Create the linkdrop
+    await creator.call(
+        root,
+        'send',
+        { public_key: publicKeys[0] },
+        { attachedDeposit: NEAR.parse("5 N").toString() }
+    );
+
+    const newAccount = await root.getAccount(`smart.${root.accountId}`);
+    t.is(await newAccount.exists(), false);
+
+    // Claim into an account the claimer knows doesn't exist yet
+    await root.setKey(keys[0]);
+    const res = await root.callRaw(
+        root,
+        'smart_claim',
+        { account_id: newAccount.accountId, account_exists: false, public_key: publicKeys[1] },
+        { gas: "300000000000000" }
+    );
+
+    // There were no failures
+    const errors = displayFailureLog(res);
+    t.is(errors.length, 0);
+
+    // The account was created with the key and the drop balance
+    t.is(await newAccount.exists(), true);
+    const newAccountKeys = await root.viewAccessKeys(newAccount.accountId);
+    t.is(newAccountKeys.keys[0].public_key, publicKeys[1]);
+    const balance = await newAccount.balance();
+    t.true(balance.total.gte(NEAR.parse("3.9 N")));
+});
+
+test('Smart claim deposits to an existing account', async t => {
+    const { root, creator, claimer } = t.context.accounts;
+    const { keys, publicKeys } = await generateKeyPairs(1);
+
+    // Create the linkdrop
+    await creator.call(
+        root,
+        'send',
+        { public_key: publicKeys[0] },
+        { attachedDeposit: NEAR.parse("5 N").toString() }
+    );
+    const claimerBalance = await claimer.balance();
+
+    // Claim into an account the claimer knows exists
+    await root.setKey(keys[0]);
+    const res = await root.callRaw(
+        root,
+        'smart_claim',
+        { account_id: claimer.accountId, account_exists: true },
+        { gas: "300000000000000" }
+    );
+
+    // There were no failures
+    const errors = displayFailureLog(res);
+    t.is(errors.length, 0);
+
+    // The claimer received the drop and the drop is gone
+    const newClaimerBalance = await claimer.balance();
+    t.true(newClaimerBalance.total.gt(claimerBalance.total.add(NEAR.parse("3.9 N"))));
+    const keysOnLinkdrop = await root.viewAccessKeys(root.accountId);
+    t.false(keysOnLinkdrop.keys.some((key: any) => key.public_key === publicKeys[0]));
+});
+
+test('Heavy create account and claim fits in the default allowance', async t => {
+    const { root, creator } = t.context.accounts;
+    const { keys, publicKeys } = await generateKeyPairs(2);
+
+    // Create the linkdrop with the default 1 NEAR access key allowance
+    await creator.call(
+        root,
+        'send',
+        { public_key: publicKeys[0] },
+        { attachedDeposit: NEAR.parse("10 N").toString() }
+    );
+
+    const newAccount = await root.getAccount(`heavy.${root.accountId}`);
+    const contractBytes = readFileSync('./__tests__/ext-wasm/nft-tutorial.wasm').toString('base64');
+
+    // Claim into a new account with a contract deployed, using the maximum gas.
+    // The allowance is charged for all the prepaid gas up front (300 Tgas at the minimum gas price is 0.03 NEAR),
+    // so the default allowance must not be rejected with a NotEnoughAllowance error.
+    await root.setKey(keys[0]);
+    const res = await root.callRaw(
+        root,
+        'create_account_and_claim',
+        {
+            new_account_id: newAccount.accountId,
+            new_public_key: publicKeys[1],
+            contract_bytes: contractBytes,
+        },
+        { gas: "300000000000000" }
+    );
+
+    // There were no failures and the new account exists
+    const errors = displayFailureLog(res);
+    t.is(errors.length, 0);
+    t.is(await newAccount.exists(), true);
+
+    // Record what the claim burnt, to tune ACCESS_KEY_ALLOWANCE against
+    const outcome: any = res.result;
+    const tokensBurnt = [outcome.transaction_outcome, ...outcome.receipts_outcome]
+        .reduce((sum: bigint, { outcome }: any) => sum + BigInt(outcome.tokens_burnt), BigInt(0));
+    const gasBurnt = [outcome.transaction_outcome, ...outcome.receipts_outcome]
+        .reduce((sum: number, { outcome }: any) => sum + outcome.gas_burnt, 0);
+    t.log(`create_account_and_claim with a contract burnt ${gasBurnt} gas, ${tokensBurnt} yoctoNEAR`);
+    t.true(tokensBurnt < BigInt(NEAR.parse("1 N").toString()));
+});
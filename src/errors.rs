@@ -0,0 +1,226 @@
+use crate::*;
+
+/// Errors returned by the contract methods, e.g. for invalid inputs, missing drops or unauthorized callers. The message of each variant
+/// is what the contract panics with when the error is not handled by the caller.
+#[derive(Debug, PartialEq)]
+pub enum LinkdropError {
+    NoCreateOptions,
+    ConflictingContractBytes,
+    EmptyContractBytes,
+    TooManyKeys,
+    DuplicateKey,
+    EmptyMethodName,
+    CommaInMethodName,
+    InvalidCallbackGas,
+    UnknownLabeledKey,
+    LabelTooLong,
+    KeyNotRegistered,
+    ContractTooLarge,
+    KeyMissing,
+    InsufficientDeposit,
+    Unauthorized,
+}
+
+impl AsRef<str> for LinkdropError {
+    fn as_ref(&self) -> &str {
+        match self {
+            LinkdropError::NoCreateOptions => "Cannot create account with no options. Please specify either contract bytes, full access keys, or limited access keys.",
+            LinkdropError::ConflictingContractBytes => "Cannot give contract bytes and base64 contract byte string at the same time.",
+            LinkdropError::EmptyContractBytes => "Contract bytes cannot be empty",
+            LinkdropError::TooManyKeys => "Too many access keys, at most MAX_KEYS_PER_ACCOUNT can be added",
+            LinkdropError::DuplicateKey => "Duplicate public key across access keys",
+            LinkdropError::EmptyMethodName => "Method names cannot be empty",
+            LinkdropError::CommaInMethodName => "Method names cannot contain commas",
+            LinkdropError::InvalidCallbackGas => "Callback gas must be between MIN_CALLBACK_GAS and MAX_CALLBACK_GAS",
+            LinkdropError::UnknownLabeledKey => "Labels can only be given to keys added to the account",
+            LinkdropError::LabelTooLong => "Key label exceeds MAX_KEY_LABEL_LENGTH",
+            LinkdropError::KeyNotRegistered => "This key is not registered for a drop",
+            LinkdropError::ContractTooLarge => "Contract too large",
+            LinkdropError::KeyMissing => "Key is missing",
+            LinkdropError::InsufficientDeposit => "Deposit must be greater than zero",
+            LinkdropError::Unauthorized => "Only the owner can call this method",
+        }
+    }
+}
+
+/// Checks everything about `CreateAccountOptions` that does not depend on the attached deposit.
+/// Shared by `create_account_advanced` and the views validating its options.
+pub(crate) fn validate_create_options(options: &CreateAccountOptions) -> Result<(), LinkdropError> {
+    let full_keys = options.full_access_keys.iter().flatten();
+    let limited_keys = options.limited_access_keys.iter().flatten().map(|key_info| &key_info.public_key);
+    let limited_keys_v2 = options.limited_access_keys_v2.iter().flatten().map(|key_info| &key_info.public_key);
+
+    let has_keys = options.full_access_keys.is_some() || options.limited_access_keys.is_some() || options.limited_access_keys_v2.is_some();
+    let has_contract = options.contract_bytes.is_some() || options.contract_bytes_base64.is_some();
+    if !has_keys && !has_contract {
+        return Err(LinkdropError::NoCreateOptions);
+    }
+    if options.contract_bytes.is_some() && options.contract_bytes_base64.is_some() {
+        return Err(LinkdropError::ConflictingContractBytes);
+    }
+    // Deploying empty code would leave the new account with a broken contract
+    if matches!(options.contract_bytes(), Some(bytes) if bytes.is_empty()) {
+        return Err(LinkdropError::EmptyContractBytes);
+    }
+
+    // Adding the same key twice would only fail once the account creation is already underway
+    let mut seen_keys = HashSet::new();
+    for key in full_keys.chain(limited_keys).chain(limited_keys_v2) {
+        if seen_keys.len() == MAX_KEYS_PER_ACCOUNT {
+            return Err(LinkdropError::TooManyKeys);
+        }
+        if !seen_keys.insert(key) {
+            return Err(LinkdropError::DuplicateKey);
+        }
+    }
+
+    for method_name in options.limited_access_keys_v2.iter().flatten().flat_map(|key_info| key_info.method_names.iter()) {
+        if method_name.trim().is_empty() {
+            return Err(LinkdropError::EmptyMethodName);
+        }
+        if method_name.contains(',') {
+            return Err(LinkdropError::CommaInMethodName);
+        }
+    }
+
+    for (key, label) in options.key_labels.iter().flatten() {
+        if !seen_keys.contains(key) {
+            return Err(LinkdropError::UnknownLabeledKey);
+        }
+        if label.len() > MAX_KEY_LABEL_LENGTH {
+            return Err(LinkdropError::LabelTooLong);
+        }
+    }
+
+    let callback_gas = options.callback_gas.unwrap_or(ON_CREATE_ACCOUNT_CALLBACK_GAS);
+    if !(MIN_CALLBACK_GAS..=MAX_CALLBACK_GAS).contains(&callback_gas) {
+        return Err(LinkdropError::InvalidCallbackGas);
+    }
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::*;
+
+    fn pk() -> PublicKey {
+        "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz".parse().unwrap()
+    }
+
+    /// Options adding a single full access key.
+    fn options() -> CreateAccountOptions {
+        CreateAccountOptions {
+            full_access_keys: Some(vec![pk()]),
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        }
+    }
+
+    fn limited_key_v2(method_names: Vec<&str>) -> LimitedAccessKeyV2 {
+        LimitedAccessKeyV2 {
+            public_key: "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca".parse().unwrap(),
+            allowance: U128(100),
+            receiver_id: "linkdrop".parse().unwrap(),
+            method_names: method_names.into_iter().map(String::from).collect(),
+        }
+    }
+
+    #[test]
+    fn test_valid_options() {
+        assert_eq!(validate_create_options(&options()), Ok(()));
+    }
+
+    #[test]
+    fn test_no_options() {
+        let options = CreateAccountOptions { full_access_keys: None, ..options() };
+        assert_eq!(validate_create_options(&options), Err(LinkdropError::NoCreateOptions));
+    }
+
+    #[test]
+    fn test_conflicting_contract_bytes() {
+        let options = CreateAccountOptions {
+            contract_bytes: Some(vec![1]),
+            contract_bytes_base64: Some(vec![1].into()),
+            ..options()
+        };
+        assert_eq!(validate_create_options(&options), Err(LinkdropError::ConflictingContractBytes));
+    }
+
+    #[test]
+    fn test_empty_contract_bytes() {
+        let bytes = CreateAccountOptions { contract_bytes: Some(vec![]), ..options() };
+        assert_eq!(validate_create_options(&bytes), Err(LinkdropError::EmptyContractBytes));
+        let base64 = CreateAccountOptions { contract_bytes_base64: Some(Base64VecU8(vec![])), ..options() };
+        assert_eq!(validate_create_options(&base64), Err(LinkdropError::EmptyContractBytes));
+    }
+
+    #[test]
+    fn test_too_many_keys() {
+        // Distinct keys are derived from the index so only the count is over the cap
+        let keys = (0..=MAX_KEYS_PER_ACCOUNT)
+            .map(|i| {
+                let mut bytes = vec![0u8; 33];
+                bytes[1..9].copy_from_slice(&(i as u64).to_le_bytes());
+                PublicKey::try_from(bytes).unwrap()
+            })
+            .collect();
+        let options = CreateAccountOptions { full_access_keys: Some(keys), ..options() };
+        assert_eq!(validate_create_options(&options), Err(LinkdropError::TooManyKeys));
+    }
+
+    #[test]
+    fn test_duplicate_key() {
+        let options = CreateAccountOptions {
+            limited_access_keys: Some(vec![LimitedAccessKey {
+                public_key: pk(),
+                allowance: U128(100),
+                receiver_id: "linkdrop".parse().unwrap(),
+                method_names: "send".to_string(),
+            }]),
+            ..options()
+        };
+        assert_eq!(validate_create_options(&options), Err(LinkdropError::DuplicateKey));
+    }
+
+    #[test]
+    fn test_empty_method_name() {
+        let options = CreateAccountOptions {
+            limited_access_keys_v2: Some(vec![limited_key_v2(vec!["send", " "])]),
+            ..options()
+        };
+        assert_eq!(validate_create_options(&options), Err(LinkdropError::EmptyMethodName));
+    }
+
+    #[test]
+    fn test_comma_in_method_name() {
+        let options = CreateAccountOptions {
+            limited_access_keys_v2: Some(vec![limited_key_v2(vec!["send,claim"])]),
+            ..options()
+        };
+        assert_eq!(validate_create_options(&options), Err(LinkdropError::CommaInMethodName));
+    }
+
+    #[test]
+    fn test_invalid_callback_gas() {
+        let options = CreateAccountOptions { callback_gas: Some(Gas(1)), ..options() };
+        assert_eq!(validate_create_options(&options), Err(LinkdropError::InvalidCallbackGas));
+    }
+
+    #[test]
+    fn test_unknown_labeled_key() {
+        let other_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca".parse().unwrap();
+        let options = CreateAccountOptions {
+            key_labels: Some(vec![(other_pk, "label".to_string())]),
+            ..options()
+        };
+        assert_eq!(validate_create_options(&options), Err(LinkdropError::UnknownLabeledKey));
+    }
+}
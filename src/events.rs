@@ -0,0 +1,105 @@
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json;
+
+use crate::*;
+
+/// Standard name of the NEP-297 events emitted by this contract.
+const EVENT_STANDARD: &str = "linkdrop";
+
+/// Version of the events emitted by this contract.
+const EVENT_VERSION: &str = "1.0.0";
+
+/// Events emitted by the contract, serialized as the `event` and `data` fields of a NEP-297 log.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    CreateAccountAdvanced(Vec<CreateAccountAdvancedLog>),
+    Claim(Vec<ClaimLog>),
+    Refund(Vec<RefundLog>),
+    NftAttach(Vec<NftAttachLog>),
+}
+
+/// Summary of what `create_account_advanced` did, passed along to its callback.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CreateAccountAdvancedLog {
+    /// The account being created.
+    pub account_id: AccountId,
+    /// Number of full access keys added.
+    pub full_access_keys: u32,
+    /// Number of function call access keys added.
+    pub limited_access_keys: u32,
+    /// SHA-256 hash of the deployed contract, if any was deployed.
+    pub contract_hash: Option<Base58CryptoHash>,
+    /// yoctoNEAR$ amount transferred to the new account.
+    pub amount: U128,
+}
+
+/// A drop, or part of one with `claim_amount`, paid out to an account.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ClaimLog {
+    /// The account receiving the drop.
+    pub account_id: AccountId,
+    /// yoctoNEAR$ amount transferred to the account.
+    pub amount: U128,
+    /// Memo given by the claimer, for accounting on the recipient's side.
+    pub memo: Option<String>,
+    /// Message attached by the funder with `send_with_message`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Reason logged when funds are returned because the account could not be created.
+pub const REFUND_REASON_CREATION_FAILED: &str = "creation_failed";
+
+/// Funds returned after an account creation failed: to the caller for `create_account` and
+/// `create_account_advanced`, or back into the drop for `create_account_and_claim`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RefundLog {
+    /// The account that could not be created.
+    pub account_id: AccountId,
+    /// yoctoNEAR$ amount returned.
+    pub amount: U128,
+    /// Why the funds were returned, e.g. `creation_failed`.
+    pub reason: String,
+}
+
+/// NFT attached to a drop by `nft_on_transfer`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftAttachLog {
+    /// The funder of the drop, who owned the token.
+    pub funder_id: AccountId,
+    /// The account that made the transfer: the funder, or an account it approved.
+    pub sender_id: AccountId,
+    /// The NEP-171 contract of the token.
+    pub contract_id: AccountId,
+    /// The token now held by the drop.
+    pub token_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: EventKind,
+}
+
+/// Logs the event in the NEP-297 `EVENT_JSON:` format.
+pub fn emit(event: EventKind) {
+    let log = EventLog {
+        standard: EVENT_STANDARD,
+        version: EVENT_VERSION,
+        event,
+    };
+    env::log_str(&format!(
+        "EVENT_JSON:{}",
+        serde_json::to_string(&log).expect("Failed to serialize event")
+    ));
+}
@@ -1,35 +1,221 @@
 use borsh::{BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{UnorderedMap};
-use near_sdk::json_types::{U128};
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap, UnorderedSet, Vector};
+use std::collections::HashSet;
+use near_sdk::json_types::{Base58CryptoHash, Base64VecU8, U128, U64};
 use near_sdk::{
-    env, ext_contract, near_bindgen, PanicOnDefault, AccountId, Balance, Promise, PromiseResult, PublicKey, Gas,
+    env, ext_contract, near_bindgen, PanicOnDefault, AccountId, Balance, BlockHeight, CryptoHash, Promise, PromiseOrValue, PromiseResult, PublicKey, Gas,
 };
 
+mod errors;
+mod events;
+mod migrate;
 mod models;
+mod nft;
+mod storage;
+use errors::*;
+use events::*;
 use models::*;
+use nft::*;
 
 #[near_bindgen]
 #[derive(PanicOnDefault, BorshDeserialize, BorshSerialize)]
 pub struct LinkDrop {
-    pub accounts: UnorderedMap<PublicKey, Balance>,
+    pub accounts: UnorderedMap<PublicKey, DropInfo>,
+    /// Sum of the balances of all outstanding drops.
+    pub total_locked: Balance,
+    /// Account allowed to manage admins.
+    pub owner_id: AccountId,
+    /// Accounts allowed to call `create_account_advanced` alongside the owner.
+    pub admins: UnorderedSet<AccountId>,
+    /// Base prefix under which all the collections of this contract are stored.
+    pub storage_prefix: Vec<u8>,
+    /// Whether sends, claims and account creation are halted.
+    pub paused: bool,
+    /// Number of successful claims since deployment, partial withdrawals included.
+    pub claimed_count: u64,
+    /// Sum of the balances paid out by successful claims.
+    pub claimed_amount: Balance,
+    /// Number of drops taken back by their funders since deployment.
+    pub reclaimed_count: u64,
+    /// Drop counters per funder.
+    pub funder_stats: LookupMap<AccountId, FunderStats>,
+    /// Allowance given to the access key of new drops. Zero gives the keys an unlimited allowance.
+    pub access_key_allowance: Balance,
+    /// Sum of the access key allowances of all outstanding drops.
+    pub locked_allowances: Balance,
+    /// Labels of keys added by `create_account_advanced`, per created account.
+    pub key_labels: LookupMap<(AccountId, PublicKey), String>,
+    /// Share of each `send` deposit kept as a fee for the owner, in basis points.
+    pub fee_basis_points: u16,
+    /// Fees taken from sends and not yet withdrawn by the owner.
+    pub owner_fees_collected: Balance,
+    /// Minimum time in nanoseconds between two `claim_amount` withdrawals from the same drop.
+    pub claim_cooldown_ns: u64,
+    /// Keys of the outstanding drops of each funder.
+    pub drops_by_funder: LookupMap<AccountId, UnorderedSet<PublicKey>>,
+    /// Account name patterns that cannot be created: `prefix*`, `*suffix` or an exact name.
+    pub reserved_patterns: UnorderedSet<String>,
+    /// Largest contract, in bytes, `create_account_advanced` accepts to deploy.
+    pub max_contract_bytes: u64,
+    /// Number of claims of each drop received by each account, tracked while `max_claims_per_account` is set.
+    pub account_claims: LookupMap<(PublicKey, AccountId), u32>,
+    /// Most claims of the same drop a single account can receive, e.g. from `claim_amount`. Zero means no limit.
+    pub max_claims_per_account: u32,
+    /// Failed `create_account` calls waiting for `retry_failed_creations`.
+    pub retry_queue: Vector<FailedCreation>,
+    /// Sum of the amounts held for the entries of `retry_queue`.
+    pub retry_locked: Balance,
+    /// Times a failed `create_account` is queued for another attempt before it is refunded. Zero refunds right away.
+    pub max_creation_retries: u32,
+    /// NEP-145 storage balances of the funders who pre-pay for the storage of their drops.
+    pub storage_balances: LookupMap<AccountId, StorageBalance>,
+    /// Sum of the storage balances not used by drops yet.
+    pub storage_available_total: Balance,
+    /// yoctoNEAR$ set aside to sponsor accounts created by `create_account_and_claim`.
+    pub sponsorship_reserve: Balance,
+    /// Amount each account created by `create_account_and_claim` gets from the reserve on top of the drop.
+    /// Zero disables sponsorship.
+    pub sponsorship_amount: Balance,
+    /// Keys whose claim is waiting for `on_account_created_and_claimed`.
+    pub claiming: LookupSet<PublicKey>,
+    /// Comma-separated methods the access keys of new drops can call, unless set per drop with `send_advanced` or
+    /// `send_with_methods`.
+    pub default_claim_methods: String,
+    /// Balances of the previous state layout still to be moved by `migrate_drops`, `None` once all are moved.
+    pub legacy_accounts: Option<UnorderedMap<PublicKey, Balance>>,
 }
 
-/// Access key allowance for linkdrop keys.
+/// Default access key allowance for linkdrop keys.
 const ACCESS_KEY_ALLOWANCE: u128 = 1_000_000_000_000_000_000_000_000;
 
 /// Gas attached to the callback from account creation.
 pub const ON_CREATE_ACCOUNT_CALLBACK_GAS: Gas = Gas(13_000_000_000_000);
 
+/// Gas used by `create_account_and_claim` itself, without its callback.
+const CREATE_ACCOUNT_AND_CLAIM_GAS: Gas = Gas(20_000_000_000_000);
+
+/// Storage in bytes kept unstaked on accounts created with a stake, on top of their contract code.
+const NEW_ACCOUNT_STORAGE_BYTES: u64 = 1_000;
+
+/// Storage in bytes budgeted for each access key added to an account created by `create_account_advanced`.
+const ACCESS_KEY_STORAGE_BYTES: u64 = 100;
+
+/// Maximum number of keys `create_account_advanced` adds to a new account.
+const MAX_KEYS_PER_ACCOUNT: usize = 100;
+
+/// Bounds of the callback gas that can be requested through `CreateAccountOptions`.
+const MIN_CALLBACK_GAS: Gas = Gas(5_000_000_000_000);
+const MAX_CALLBACK_GAS: Gas = Gas(50_000_000_000_000);
+
+/// Maximum length in bytes of the metadata attached to a drop.
+const MAX_METADATA_LENGTH: usize = 256;
+
+/// Maximum length in bytes of the memo attached to a claim.
+const MAX_MEMO_LENGTH: usize = 128;
+
+/// Maximum length in bytes of the message a funder attaches to a drop.
+const MAX_CLAIM_MESSAGE_LENGTH: usize = 512;
+
+/// Maximum length in bytes of a key label given to `create_account_advanced`.
+const MAX_KEY_LABEL_LENGTH: usize = 64;
+
+/// Account allowed to create short top-level accounts.
+const REGISTRAR_ACCOUNT_ID: &str = "registrar";
+
+/// Gas kept aside in `claim_and_call` for the claim itself.
+const CLAIM_AND_CALL_GAS_BUFFER: Gas = Gas(20_000_000_000_000);
+
 /// Methods callable by the function call access key
-const ACCESS_KEY_METHOD_NAMES: &str = "claim,create_account_and_claim";
+const ACCESS_KEY_METHOD_NAMES: &str = "claim,create_account_and_claim,claim_and_call,commit_claim,reveal_claim,claim_amount,claim_with_receipt,ensure_account_and_claim,smart_claim,create_named_claim";
+
+/// Number of blocks a `commit_claim` commitment keeps other claims of the drop out.
+const CLAIM_COMMIT_LOCK_BLOCKS: BlockHeight = 100;
+
+/// Highest fee the owner can set, in basis points (10%).
+const MAX_FEE_BASIS_POINTS: u16 = 1_000;
+
+/// Maximum number of keys `cancel_many` and `reclaim_batch` take in one call.
+const MAX_CANCEL_KEYS: usize = 50;
+
+/// Default largest contract `create_account_advanced` deploys (4 MB).
+const DEFAULT_MAX_CONTRACT_BYTES: u64 = 4 * 1024 * 1024;
+
+/// NEP-171 methods used to deliver NFT drops.
+#[ext_contract(ext_nft)]
+pub trait NonFungibleToken {
+    fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: String,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    );
+}
 
 #[ext_contract(ext_self)]
 pub trait ExtLinkDrop {
     /// Callback after plain account creation.
-    fn on_account_created(&mut self, predecessor_account_id: AccountId, amount: U128) -> bool;
+    fn on_account_created(
+        &mut self,
+        new_account_id: AccountId,
+        predecessor_account_id: AccountId,
+        amount: U128,
+        public_key: Option<PublicKey>,
+        retries: u32,
+        advanced: Option<AdvancedCreation>,
+    ) -> bool;
 
     /// Callback after creating account and claiming linkdrop.
-    fn on_account_created_and_claimed(&mut self, amount: U128) -> bool;
+    fn on_account_created_and_claimed(
+        &mut self,
+        #[serializer(borsh)] new_account_id: AccountId,
+        #[serializer(borsh)] drop: DropInfo,
+        #[serializer(borsh)] sponsored: Balance,
+    ) -> bool;
+
+    /// Callback after `ensure_account_and_claim` tried to create the account.
+    fn on_ensure_account(
+        &mut self,
+        #[serializer(borsh)] account_id: AccountId,
+        #[serializer(borsh)] drop: DropInfo,
+        #[serializer(borsh)] callback_gas: Gas,
+    ) -> PromiseOrValue<bool>;
+
+    /// Callback after the owner withdrew the NFT of a drop.
+    fn on_nft_withdrawn(
+        &mut self,
+        #[serializer(borsh)] public_key: PublicKey,
+        #[serializer(borsh)] drop: DropInfo,
+        #[serializer(borsh)] to: AccountId,
+    ) -> bool;
+}
+
+fn assert_memo(memo: &Option<String>) {
+    if let Some(memo) = memo.as_ref() {
+        assert!(memo.len() <= MAX_MEMO_LENGTH, "Memo exceeds MAX_MEMO_LENGTH");
+    }
+}
+
+/// Balance an account created by `create_account_advanced` keeps unstaked to pay for its storage,
+/// including its access keys and the contract deployed to it, if any.
+fn new_account_storage_reserve(options: &CreateAccountOptions) -> Balance {
+    let storage_bytes = NEW_ACCOUNT_STORAGE_BYTES
+        + options.key_count() as u64 * ACCESS_KEY_STORAGE_BYTES
+        + options.contract_bytes().map_or(0, |bytes| bytes.len() as u64);
+    Balance::from(storage_bytes) * env::storage_byte_cost()
+}
+
+/// Balance `create_account_free` gives the new account: enough to pay for its storage and full access key.
+fn free_account_amount() -> Balance {
+    Balance::from(NEW_ACCOUNT_STORAGE_BYTES + ACCESS_KEY_STORAGE_BYTES) * env::storage_byte_cost()
+}
+
+/// Gas for `on_account_created_and_claimed`. Delivering an NFT happens in the callback and needs extra gas.
+fn claim_callback_gas(drop: &DropInfo) -> Gas {
+    match drop.nft {
+        Some(_) => ON_CREATE_ACCOUNT_CALLBACK_GAS + GAS_FOR_NFT_TRANSFER,
+        None => ON_CREATE_ACCOUNT_CALLBACK_GAS,
+    }
 }
 
 fn is_promise_success() -> bool {
@@ -44,40 +230,445 @@ fn is_promise_success() -> bool {
     }
 }
 
+/// Creates the account of a `create_account` call, or of a retry of one, with its full access key and
+/// deposit. The callback refunds the deposit or queues another attempt if the creation fails.
+fn internal_create_account(creation: FailedCreation) -> Promise {
+    Promise::new(creation.new_account_id.clone())
+        .create_account()
+        .add_full_access_key(creation.public_key.clone())
+        .transfer(creation.amount.0)
+        .then(
+            LinkDrop::ext(env::current_account_id())
+                .with_static_gas(ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                .on_account_created(
+                    creation.new_account_id,
+                    creation.predecessor_account_id,
+                    creation.amount,
+                    Some(creation.public_key),
+                    creation.retries,
+                    None
+                )
+        )
+}
+
+/// Whether the account is an implicit account, i.e. 64 lowercase hex characters. Those cannot be created
+/// with `CreateAccount`: they come into existence when they first receive a transfer.
+fn is_implicit_account(account_id: &AccountId) -> bool {
+    account_id.as_str().len() == 64
+        && account_id.as_str().bytes().all(|c| c.is_ascii_digit() || (b'a'..=b'f').contains(&c))
+}
+
+/// Panics if a comma-separated list of access key methods has an empty name, which the runtime would reject,
+/// or a method outside of ACCESS_KEY_METHOD_NAMES: drop keys are keys of this contract, so any other method
+/// would let them call the owner-only ones.
+fn assert_method_names(method_names: &str) {
+    for name in method_names.split(',') {
+        assert!(!name.is_empty(), "Method names cannot be empty");
+        assert!(
+            ACCESS_KEY_METHOD_NAMES.split(',').any(|claim_method| claim_method == name),
+            "Drop keys can only call claim methods"
+        );
+    }
+}
+
+/// Sends what is left of a claimed drop back to its funder, if known.
+fn refund_funder(funder_id: Option<AccountId>, amount: Balance) {
+    if let Some(funder_id) = funder_id {
+        if amount > 0 {
+            Promise::new(funder_id).transfer(amount);
+        }
+    }
+}
+
 #[near_bindgen]
 impl LinkDrop {
-    /// Initializes the contract with an empty map for the accounts
+    /// Initializes the contract with an empty map for the accounts and the given owner.
+    /// All collections are stored under `prefix` (empty by default), which keeps drop pools of
+    /// different deployments isolated. The prefix is fixed at init: changing it later requires
+    /// moving every stored entry. `migrate` uses MIGRATED_STORAGE_PREFIX, as the previous layout
+    /// occupies the default empty prefix.
     #[init]
-    pub fn new() -> Self {
+    pub fn new(owner_id: AccountId, prefix: Option<Vec<u8>>) -> Self {
+        let storage_prefix = prefix.unwrap_or_default();
         Self { 
-            accounts: UnorderedMap::new(b"a") 
+            accounts: UnorderedMap::new(StorageKey::Accounts.with_prefix(&storage_prefix)),
+            total_locked: 0,
+            owner_id,
+            admins: UnorderedSet::new(StorageKey::Admins.with_prefix(&storage_prefix)),
+            funder_stats: LookupMap::new(StorageKey::FunderStats.with_prefix(&storage_prefix)),
+            key_labels: LookupMap::new(StorageKey::KeyLabels.with_prefix(&storage_prefix)),
+            drops_by_funder: LookupMap::new(StorageKey::DropsByFunder.with_prefix(&storage_prefix)),
+            reserved_patterns: UnorderedSet::new(StorageKey::ReservedPatterns.with_prefix(&storage_prefix)),
+            account_claims: LookupMap::new(StorageKey::AccountClaims.with_prefix(&storage_prefix)),
+            retry_queue: Vector::new(StorageKey::RetryQueue.with_prefix(&storage_prefix)),
+            storage_balances: LookupMap::new(StorageKey::StorageBalances.with_prefix(&storage_prefix)),
+            claiming: LookupSet::new(StorageKey::Claiming.with_prefix(&storage_prefix)),
+            storage_prefix,
+            paused: false,
+            claimed_count: 0,
+            claimed_amount: 0,
+            reclaimed_count: 0,
+            access_key_allowance: ACCESS_KEY_ALLOWANCE,
+            locked_allowances: 0,
+            fee_basis_points: 0,
+            owner_fees_collected: 0,
+            claim_cooldown_ns: 0,
+            max_contract_bytes: DEFAULT_MAX_CONTRACT_BYTES,
+            max_claims_per_account: 0,
+            retry_locked: 0,
+            max_creation_retries: 0,
+            storage_available_total: 0,
+            sponsorship_reserve: 0,
+            sponsorship_amount: 0,
+            default_claim_methods: ACCESS_KEY_METHOD_NAMES.to_string(),
+            legacy_accounts: None,
         }
     }
 
     /// Allows given public key to claim sent balance.
-    /// Takes ACCESS_KEY_ALLOWANCE as fee from deposit to cover account creation via an access key.
+    /// Takes the access key allowance as fee from deposit to cover account creation via an access key.
     #[payable]
     pub fn send(&mut self, public_key: PublicKey) -> Promise {
+        self.internal_send(public_key, DropInfo::new(0))
+    }
+
+    /// Same as `send`, but panics if the key already has a drop instead of topping it up.
+    #[payable]
+    pub fn send_once(&mut self, public_key: PublicKey) -> Promise {
+        assert!(self.accounts.get(&public_key).is_none(), "Key already has a drop");
+        self.send(public_key)
+    }
+
+    /// Adds the attached deposit, minus the owner fee, to the balance of an existing drop.
+    /// Unlike `send`, the access key is not added again.
+    #[payable]
+    #[handle_result]
+    pub fn top_up(&mut self, public_key: PublicKey) -> Result<(), LinkdropError> {
+        self.assert_not_paused();
+        if self.accounts.get(&public_key).is_none() {
+            return Err(LinkdropError::KeyMissing);
+        }
+        if env::attached_deposit() == 0 {
+            return Err(LinkdropError::InsufficientDeposit);
+        }
+        let balance = self.take_fee(env::attached_deposit());
+        self.internal_add_to_drop(&public_key, DropInfo::new(balance));
+        Ok(())
+    }
+
+    /// Same as `send`, but the drop can only be claimed by also presenting the password
+    /// whose SHA-256 hash is `password_hash`.
+    #[payable]
+    pub fn send_protected(&mut self, public_key: PublicKey, password_hash: Base58CryptoHash) -> Promise {
+        self.internal_send(public_key, DropInfo {
+            password_hash: Some(password_hash.into()),
+            ..DropInfo::new(0)
+        })
+    }
+
+    /// Same as `send`, but attaches a free-form metadata string (e.g. a campaign ID) to the drop.
+    /// The metadata is returned by `get_key_information` and is capped at MAX_METADATA_LENGTH bytes.
+    #[payable]
+    pub fn send_with_metadata(&mut self, public_key: PublicKey, metadata: Option<String>) -> Promise {
+        if let Some(metadata) = metadata.as_ref() {
+            assert!(metadata.len() <= MAX_METADATA_LENGTH, "Metadata exceeds MAX_METADATA_LENGTH");
+        }
+        self.internal_send(public_key, DropInfo {
+            metadata,
+            ..DropInfo::new(0)
+        })
+    }
+
+    /// Same as `send`, but attaches a message from the funder (e.g. "Happy Birthday from Alice") that is
+    /// logged in the claim event for wallets to display. Capped at MAX_CLAIM_MESSAGE_LENGTH bytes.
+    #[payable]
+    pub fn send_with_message(&mut self, public_key: PublicKey, claim_message: String) -> Promise {
         assert!(
-            env::attached_deposit() > ACCESS_KEY_ALLOWANCE,
-            "Attached deposit must be greater than ACCESS_KEY_ALLOWANCE"
+            claim_message.len() <= MAX_CLAIM_MESSAGE_LENGTH,
+            "Message exceeds MAX_CLAIM_MESSAGE_LENGTH"
+        );
+        self.internal_send(public_key, DropInfo {
+            claim_message: Some(claim_message),
+            ..DropInfo::new(0)
+        })
+    }
+
+    /// Same as `send`, but the drop is a pool shared by `uses` claims, each paying a random amount between
+    /// `min` and `max` drawn at claim time. A claim never pays more than is left in the pool; once the uses
+    /// run out, the key is deleted and the rest of the pool goes back to the funder.
+    /// Random drops can only be claimed with `claim`, at most once per `claim_cooldown_ns`, and cannot hold an NFT.
+    #[payable]
+    pub fn send_random(&mut self, public_key: PublicKey, min: U128, max: U128, uses: u32) -> Promise {
+        assert!(min.0 > 0 && min.0 <= max.0, "Invalid random range");
+        assert!(uses > 0, "Uses must be greater than zero");
+        self.internal_send(public_key, DropInfo {
+            random: Some(RandomRange { min: min.0, max: max.0, uses_left: uses }),
+            ..DropInfo::new(0)
+        })
+    }
+
+    /// Same as `send`, but if `create_account_and_claim` fails to create the account, the balance and NFT of the
+    /// drop are paid to `fallback_account` instead of being put back under the key.
+    #[payable]
+    pub fn send_with_fallback(&mut self, public_key: PublicKey, fallback_account: AccountId) -> Promise {
+        self.internal_send(public_key, DropInfo {
+            fallback_account: Some(fallback_account),
+            ..DropInfo::new(0)
+        })
+    }
+
+    /// Same as `send`, but the access key of the drop can only call the given comma-separated methods
+    /// instead of `default_claim_methods`, e.g. `claim` alone for drops that must not be claimed with
+    /// `claim_and_call`.
+    #[payable]
+    pub fn send_with_methods(&mut self, public_key: PublicKey, method_names: String) -> Promise {
+        assert_method_names(&method_names);
+        self.internal_send(public_key, DropInfo {
+            method_names: Some(method_names),
+            ..DropInfo::new(0)
+        })
+    }
+
+    /// Same as `send`, but the account created when claiming with `create_account_and_claim` also
+    /// receives the given function call keys (e.g. for app onboarding). Claims into existing accounts ignore them.
+    #[payable]
+    pub fn send_with_new_account_keys(
+        &mut self,
+        public_key: PublicKey,
+        new_account_limited_keys: Vec<LimitedAccessKey>,
+    ) -> Promise {
+        self.internal_send(public_key, DropInfo {
+            new_account_limited_keys: Some(new_account_limited_keys),
+            ..DropInfo::new(0)
+        })
+    }
+
+    /// Same as `send`, but the caller picks the drop balance, the allowance of its access key and the
+    /// comma-separated claim methods the key can call, e.g. a larger allowance for a `create_account_and_claim`
+    /// that deploys a contract. The allowance cannot be zero, which would make it unlimited.
+    /// Attached deposit must equal `balance` plus `allowance`, and the owner fee is taken out of the balance.
+    #[payable]
+    pub fn send_advanced(
+        &mut self,
+        public_key: PublicKey,
+        balance: U128,
+        allowance: U128,
+        method_names: String,
+    ) -> Promise {
+        self.assert_not_paused();
+        assert!(balance.0 > 0, "Drop amount must be greater than zero");
+        assert!(allowance.0 > 0, "Allowance must be greater than zero");
+        assert_method_names(&method_names);
+        assert_eq!(
+            env::attached_deposit(),
+            balance.0.checked_add(allowance.0).expect("Drop total overflow"),
+            "Attached deposit must equal the balance plus the allowance"
         );
-        let pk = public_key.into();
-        let value = self.accounts.get(&pk).unwrap_or(0);
-        self.accounts.insert(
-            &pk,
-            &(value + env::attached_deposit() - ACCESS_KEY_ALLOWANCE),
+        let balance = self.take_drop_balance(allowance.0);
+        let initial_storage = env::storage_usage();
+        self.internal_add_to_drop(&public_key, DropInfo {
+            funder_id: Some(env::predecessor_account_id()),
+            allowance: allowance.0,
+            method_names: Some(method_names.clone()),
+            ..DropInfo::new(balance)
+        });
+        self.charge_storage(&public_key, &env::predecessor_account_id(), initial_storage);
+        Promise::new(env::current_account_id()).add_access_key(
+            public_key,
+            allowance.0,
+            env::current_account_id(),
+            method_names,
+        )
+    }
+
+    /// Same as `send`, but paid for by the caller (e.g. a relayer sponsoring users without NEAR) on behalf of
+    /// `funder_id`, who is recorded as the funder of the drop and can reclaim it.
+    /// Attached deposit must equal `amount` plus the access key allowance, and the owner fee is taken out of `amount`.
+    #[payable]
+    pub fn send_on_behalf(&mut self, funder_id: AccountId, public_key: PublicKey, amount: U128) -> Promise {
+        self.assert_not_paused();
+        assert!(amount.0 > 0, "Drop amount must be greater than zero");
+        let allowance = self.access_key_allowance;
+        assert_eq!(
+            env::attached_deposit(),
+            amount.0.checked_add(allowance).expect("Drop total overflow"),
+            "Attached deposit must equal the amount plus the access key allowance"
         );
+        let balance = self.take_drop_balance(allowance);
+        let initial_storage = env::storage_usage();
+        self.internal_add_to_drop(&public_key, DropInfo {
+            funder_id: Some(funder_id),
+            allowance,
+            method_names: self.drop_method_names(),
+            ..DropInfo::new(balance)
+        });
+        self.charge_storage(&public_key, &env::predecessor_account_id(), initial_storage);
         Promise::new(env::current_account_id()).add_access_key(
-            pk,
-            ACCESS_KEY_ALLOWANCE,
+            public_key,
+            allowance,
             env::current_account_id(),
-            ACCESS_KEY_METHOD_NAMES.to_string(),
+            self.default_claim_methods.clone(),
         )
     }
 
+    /// Allows each given public key to claim its paired balance in a single call.
+    /// Attached deposit must cover the sum of all balances, the access key allowance for every key
+    /// and, unless the caller holds a storage balance paying for it, the storage taken by the new drops.
+    /// Any excess is refunded to the caller. The owner fee is taken out of each balance.
+    #[payable]
+    pub fn send_batch(&mut self, drops: Vec<(PublicKey, U128)>) -> Promise {
+        self.assert_not_paused();
+        assert!(!drops.is_empty(), "Batch must contain at least one drop");
+        let allowance = self.access_key_allowance;
+        let mut seen = HashSet::new();
+        let mut total: Balance = 0;
+        for (pk, amount) in drops.iter() {
+            assert!(seen.insert(pk), "Duplicate public key in batch");
+            assert!(amount.0 > 0, "Drop amount must be greater than zero");
+            total = total
+                .checked_add(amount.0 + allowance)
+                .expect("Batch total overflow");
+        }
+
+        let initial_storage = env::storage_usage();
+        let mut promise = Promise::new(env::current_account_id());
+        for (pk, amount) in drops {
+            let balance = self.take_fee(amount.0 + allowance);
+            assert!(balance > allowance, "Deposit must cover the access key allowance and the fee");
+            let drop_storage = env::storage_usage();
+            self.internal_add_to_drop(&pk, DropInfo {
+                funder_id: Some(env::predecessor_account_id()),
+                allowance,
+                method_names: self.drop_method_names(),
+                ..DropInfo::new(balance - allowance)
+            });
+            self.charge_storage(&pk, &env::predecessor_account_id(), drop_storage);
+            promise = promise.add_access_key(
+                pk,
+                allowance,
+                env::current_account_id(),
+                self.default_claim_methods.clone(),
+            );
+        }
+
+        let storage_used = if self.storage_balances.get(&env::predecessor_account_id()).is_some() {
+            0
+        } else {
+            env::storage_usage().saturating_sub(initial_storage)
+        };
+        let required = total
+            .checked_add(Balance::from(storage_used) * env::storage_byte_cost())
+            .expect("Batch total overflow");
+        let deposit = env::attached_deposit();
+        assert!(
+            deposit >= required,
+            "Attached deposit must cover all drops, their access key allowances and storage"
+        );
+        if deposit > required {
+            Promise::new(env::predecessor_account_id()).transfer(deposit - required);
+        }
+        promise
+    }
+
+    /// Moves the drop of `old_public_key` to `new_public_key`, e.g. when the wrong key was shared or it leaked
+    /// before the claim. The old access key is deleted and the new one gets the allowance of the drop.
+    /// The old key may have burned part of its allowance, so the attached deposit must pay for the new
+    /// one in full, and keys with an unlimited allowance cannot be rotated.
+    /// A pending commitment made with the old key is discarded. Only callable by the funder of the drop.
+    #[payable]
+    pub fn rotate_key(&mut self, old_public_key: PublicKey, new_public_key: PublicKey) -> Promise {
+        self.assert_not_paused();
+        assert!(
+            self.accounts.get(&new_public_key).is_none(),
+            "New public key already has a drop"
+        );
+        let drop = self.accounts.get(&old_public_key).expect("Unexpected public key");
+        assert_eq!(
+            drop.funder_id.as_ref(),
+            Some(&env::predecessor_account_id()),
+            "Only the funder of the drop can rotate its key"
+        );
+        let allowance = drop.allowance;
+        assert!(allowance > 0, "Cannot rotate a key with an unlimited allowance");
+        assert_eq!(
+            env::attached_deposit(),
+            allowance,
+            "Attached deposit must equal the allowance of the new key"
+        );
+        let method_names = drop.key_method_names().to_string();
+        self.accounts.remove(&old_public_key);
+        self.remove_funder_drop(drop.funder_id.as_ref(), &old_public_key);
+        self.add_funder_drop(drop.funder_id.as_ref(), &new_public_key);
+        self.accounts.insert(&new_public_key, &DropInfo { commitment: None, ..drop });
+        Promise::new(env::current_account_id())
+            .delete_key(old_public_key)
+            .add_access_key(
+                new_public_key,
+                allowance,
+                env::current_account_id(),
+                method_names,
+            )
+    }
+
+    /// Makes `new_funder` the funder of the drop, e.g. when a batch of drops is sold to another operator.
+    /// The right to reclaim or rotate the drop moves with it.
+    /// `new_funder` is checked to be a valid account ID when the arguments are parsed.
+    /// Only callable by the funder of the drop.
+    pub fn transfer_drop_ownership(&mut self, public_key: PublicKey, new_funder: AccountId) {
+        self.assert_not_paused();
+        let mut drop = self.accounts.get(&public_key).expect("Unexpected public key");
+        assert_eq!(
+            drop.funder_id.as_ref(),
+            Some(&env::predecessor_account_id()),
+            "Only the funder of the drop can transfer it"
+        );
+        self.remove_funder_drop(drop.funder_id.as_ref(), &public_key);
+        self.add_funder_drop(Some(&new_funder), &public_key);
+        drop.funder_id = Some(new_funder);
+        self.accounts.insert(&public_key, &drop);
+    }
+
+    /// Replaces the access key of a drop with the same key holding a fresh allowance of `access_key_allowance`,
+    /// e.g. when a key used for many `claim_amount` or random drop claims has burned its allowance. The contract
+    /// cannot read how much of it is left, so the owner decides when to refresh. The new allowance is reserved
+    /// from the unallocated balance. Only callable by the owner.
+    pub fn refresh_allowance(&mut self, public_key: PublicKey) -> Promise {
+        self.assert_owner();
+        let mut drop = self.accounts.get(&public_key).expect("Unexpected public key");
+        let allowance = self.access_key_allowance;
+        assert!(
+            self.unallocated_balance() >= allowance,
+            "Not enough free balance to refresh the allowance"
+        );
+        self.locked_allowances = self.locked_allowances.saturating_sub(drop.allowance);
+        self.lock_allowance(allowance);
+        drop.allowance = allowance;
+        let method_names = drop.key_method_names().to_string();
+        self.accounts.insert(&public_key, &drop);
+        Promise::new(env::current_account_id())
+            .delete_key(public_key.clone())
+            .add_access_key(public_key, allowance, env::current_account_id(), method_names)
+    }
+
+    /// Takes back the drops of the given keys funded by the caller: their access keys are deleted and their
+    /// balances are refunded in one transfer. Keys that are unknown, funded by someone else or hold an NFT
+    /// are skipped. Cancelling forfeits the access key allowances: the contract cannot tell how much of them
+    /// the keys burned, so they are not refunded and what is left goes to the owner with `sweep_unallocated`.
+    /// Returns the number of cancelled drops.
+    pub fn cancel_many(&mut self, public_keys: Vec<PublicKey>) -> u32 {
+        self.internal_cancel_drops(public_keys).0
+    }
+
+    /// Same as `cancel_many`, but returns the skipped keys instead, e.g. to report them when a campaign ends.
+    pub fn reclaim_batch(&mut self, public_keys: Vec<PublicKey>) -> Vec<PublicKey> {
+        self.internal_cancel_drops(public_keys).1
+    }
+
     /// Claim tokens for specific account that are attached to the public key this tx is signed with.
-    pub fn claim(&mut self, account_id: AccountId) -> Promise {
+    /// `password` is only checked for drops registered with `send_protected`.
+    /// `memo` is logged in a `claim` event and passed along with the NFT transfer, if any.
+    pub fn claim(&mut self, account_id: AccountId, password: Option<String>, memo: Option<String>) -> Promise {
+        self.assert_not_paused();
         assert_eq!(
             env::predecessor_account_id(),
             env::current_account_id(),
@@ -87,20 +678,170 @@ impl LinkDrop {
             env::is_valid_account_id(account_id.as_bytes()),
             "Invalid account id"
         );
-        let amount = self
-            .accounts
-            .remove(&env::signer_account_pk())
-            .expect("Unexpected public key");
+        assert_memo(&memo);
+        self.record_account_claim(&env::signer_account_pk(), &account_id);
+        if let Some(drop) = self.accounts.get(&env::signer_account_pk()).filter(|drop| drop.random.is_some()) {
+            return self.internal_claim_random(account_id, drop, password, memo);
+        }
+        let drop = self.internal_take_drop(password);
         Promise::new(env::current_account_id()).delete_key(env::signer_account_pk());
-        Promise::new(account_id).transfer(amount)
+        self.record_claim(drop.balance);
+        self.update_funder_stats(drop.funder_id.as_ref(), |stats| stats.claimed += 1);
+        emit(EventKind::Claim(vec![ClaimLog {
+            account_id: account_id.clone(),
+            amount: drop.balance.into(),
+            memo: memo.clone(),
+            message: drop.claim_message.clone(),
+        }]));
+        if let Some(nft) = drop.nft {
+            transfer_nft(nft, account_id.clone(), memo);
+        }
+        Promise::new(account_id).transfer(drop.balance)
+    }
+
+    /// Same as `claim`, but returns the claimed yoctoNEAR amount so wallets can read it from the
+    /// transaction result. The transfer itself happens in a separate receipt.
+    pub fn claim_with_receipt(&mut self, account_id: AccountId, password: Option<String>, memo: Option<String>) -> U128 {
+        // Random drops pay less than their balance, so the amount is read from the lifetime total
+        let claimed_before = self.claimed_amount;
+        self.claim(account_id, password, memo);
+        (self.claimed_amount - claimed_before).into()
+    }
+
+    /// Claim tokens to an existing account and then call `method_name` on it with `args`,
+    /// e.g. to stake the claimed balance right away.
+    pub fn claim_and_call(
+        &mut self,
+        account_id: AccountId,
+        method_name: String,
+        args: Base64VecU8,
+        gas: Gas,
+        password: Option<String>,
+        memo: Option<String>,
+    ) -> Promise {
+        self.assert_not_paused();
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Claim only can come from this account"
+        );
+        assert!(
+            gas <= Gas(env::prepaid_gas().0.saturating_sub(CLAIM_AND_CALL_GAS_BUFFER.0)),
+            "Not enough prepaid gas for the call"
+        );
+        assert_memo(&memo);
+        // A call back into the contract would pass every check reserved to the contract itself
+        assert_ne!(account_id, env::current_account_id(), "Cannot call the linkdrop contract itself");
+        let drop = self.internal_take_drop(password);
+        Promise::new(env::current_account_id()).delete_key(env::signer_account_pk());
+        self.record_claim(drop.balance);
+        self.update_funder_stats(drop.funder_id.as_ref(), |stats| stats.claimed += 1);
+        emit(EventKind::Claim(vec![ClaimLog {
+            account_id: account_id.clone(),
+            amount: drop.balance.into(),
+            memo: memo.clone(),
+            message: drop.claim_message.clone(),
+        }]));
+        if let Some(nft) = drop.nft {
+            transfer_nft(nft, account_id.clone(), memo);
+        }
+        Promise::new(account_id)
+            .transfer(drop.balance)
+            .function_call(method_name, args.0, 0, gas)
+    }
+
+    /// Withdraws part of the drop balance to `account_id`, e.g. for a tip jar. The key stays usable
+    /// until the balance is used up; claiming the whole remaining balance behaves like `claim`.
+    /// Withdrawals from the same drop must be at least `claim_cooldown_ns` apart.
+    #[private]
+    pub fn claim_amount(&mut self, account_id: AccountId, amount: U128, password: Option<String>) -> Promise {
+        self.assert_not_paused();
+        assert!(
+            env::is_valid_account_id(account_id.as_bytes()),
+            "Invalid account id"
+        );
+        let public_key = env::signer_account_pk();
+        self.assert_not_claiming();
+        let mut drop = self.accounts.get(&public_key).expect("Unexpected public key");
+        assert!(drop.random.is_none(), "Random drops can only be claimed with claim");
+        assert!(amount.0 > 0, "Amount must be greater than zero");
+        assert!(amount.0 <= drop.balance, "Amount exceeds the drop balance");
+        self.assert_cooldown_passed(&drop);
+        if amount.0 == drop.balance {
+            return self.claim(account_id, password, None);
+        }
+        self.assert_claimable(&drop, password);
+        self.record_account_claim(&public_key, &account_id);
+        drop.balance -= amount.0;
+        drop.last_claim_at = env::block_timestamp();
+        self.accounts.insert(&public_key, &drop);
+        self.unlock(amount.0);
+        self.record_claim(amount.0);
+        emit(EventKind::Claim(vec![ClaimLog {
+            account_id: account_id.clone(),
+            amount,
+            memo: None,
+            message: drop.claim_message.clone(),
+        }]));
+        Promise::new(account_id).transfer(amount.0)
+    }
+
+    /// First step of a front-running resistant claim, signed with the drop key like `claim`.
+    /// `commitment` is the SHA-256 hash of the receiving account ID followed by a secret salt.
+    /// For the next CLAIM_COMMIT_LOCK_BLOCKS blocks the drop can only be claimed with `reveal_claim`.
+    pub fn commit_claim(&mut self, commitment: Base58CryptoHash) {
+        self.assert_not_paused();
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Claim only can come from this account"
+        );
+        let public_key = env::signer_account_pk();
+        let mut drop = self.accounts.get(&public_key).expect("Unexpected public key");
+        if let Some(existing) = drop.commitment.as_ref() {
+            assert!(
+                env::block_height() >= existing.locked_until,
+                "Drop is locked by a pending commitment"
+            );
+        }
+        drop.commitment = Some(ClaimCommitment {
+            hash: commitment.into(),
+            locked_until: env::block_height() + CLAIM_COMMIT_LOCK_BLOCKS,
+        });
+        self.accounts.insert(&public_key, &drop);
+    }
+
+    /// Second step of a front-running resistant claim: claims the drop into `account_id` if
+    /// `sha256(account_id + salt)` matches the commitment registered with `commit_claim`.
+    pub fn reveal_claim(&mut self, account_id: AccountId, salt: String, password: Option<String>) -> Promise {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Claim only can come from this account"
+        );
+        let public_key = env::signer_account_pk();
+        let mut drop = self.accounts.get(&public_key).expect("Unexpected public key");
+        let commitment = drop.commitment.take().expect("No commitment for this drop");
+        let revealed = env::sha256_array(&[account_id.as_bytes(), salt.as_bytes()].concat());
+        assert_eq!(revealed, commitment.hash, "Reveal does not match the commitment");
+        self.accounts.insert(&public_key, &drop);
+        self.claim(account_id, password, None)
     }
 
     /// Create new account and and claim tokens to it.
+    /// Optionally deploys the given contract to the new account (e.g. a smart wallet).
+    /// While sponsorship is enabled, the new account also gets `sponsorship_amount` from the sponsorship
+    /// reserve so it can transact right away. If the reserve cannot cover it, the account only gets the drop.
+    /// Fails with `KeyNotRegistered` if the key this tx is signed with has no drop.
+    #[handle_result]
     pub fn create_account_and_claim(
         &mut self,
         new_account_id: AccountId,
         new_public_key: PublicKey,
-    ) -> Promise {
+        contract_bytes: Option<Base64VecU8>,
+        password: Option<String>,
+    ) -> Result<Promise, LinkdropError> {
+        self.assert_not_paused();
         assert_eq!(
             env::predecessor_account_id(),
             env::current_account_id(),
@@ -110,67 +851,352 @@ impl LinkDrop {
             env::is_valid_account_id(new_account_id.as_bytes()),
             "Invalid account id"
         );
-        let amount = self
-            .accounts
-            .remove(&env::signer_account_pk())
-            .expect("Unexpected public key");
-        Promise::new(new_account_id)
+        assert!(!is_implicit_account(&new_account_id), "Use claim for implicit accounts");
+        assert!(self.can_create(&new_account_id), "Cannot create this account name");
+        assert!(!self.is_reserved(&new_account_id), "Account name is reserved");
+        if let Some(bytes) = contract_bytes.as_ref() {
+            assert!(!bytes.0.is_empty(), "Contract bytes cannot be empty");
+        }
+        self.assert_not_claiming();
+        if self.accounts.get(&env::signer_account_pk()).is_none() {
+            return Err(LinkdropError::KeyNotRegistered);
+        }
+        let drop = self.internal_take_drop_in_flight(password);
+        let callback_gas = claim_callback_gas(&drop);
+        let sponsored = self.take_sponsorship();
+        let mut promise = Promise::new(new_account_id.clone())
             .create_account()
-            .add_full_access_key(new_public_key.into())
-            .transfer(amount)
+            .add_full_access_key(new_public_key.into());
+        for key_info in drop.new_account_limited_keys.iter().flatten() {
+            promise = promise.add_access_key(key_info.public_key.clone(), key_info.allowance.0, key_info.receiver_id.clone(), key_info.method_names.clone());
+        }
+        if let Some(bytes) = contract_bytes {
+            promise = promise.deploy_contract(bytes.0);
+        }
+        Ok(promise
+            .transfer(drop.balance + sponsored)
             .then(
                 Self::ext(env::current_account_id())
-                    .with_static_gas(ON_CREATE_ACCOUNT_CALLBACK_GAS)
-                    .on_account_created_and_claimed(amount.into())
-            )
+                    .with_static_gas(callback_gas)
+                    .on_account_created_and_claimed(new_account_id, drop, sponsored)
+            ))
     }
 
-    /// Create new account without linkdrop and deposit passed funds (used for creating sub accounts directly).
-    #[payable]
-    pub fn create_account(
+    /// Same as `create_account_and_claim` into `<prefix>.<this contract>`, e.g. `claim-<hash>.linkdrop.near`.
+    /// `prefix` may only contain lowercase letters, digits, `-` and `_`.
+    #[private]
+    pub fn create_named_claim(&mut self, prefix: String, new_public_key: PublicKey) -> Promise {
+        assert!(
+            !prefix.is_empty()
+                && prefix.bytes().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'-' || c == b'_'),
+            "Invalid account prefix"
+        );
+        let new_account_id: AccountId = format!("{}.{}", prefix, env::current_account_id())
+            .parse()
+            .expect("Invalid account prefix");
+        self.create_account_and_claim(new_account_id, new_public_key, None, None)
+            .unwrap_or_else(|err| panic!("{}", err.as_ref()))
+    }
+
+    /// Claims into `account_id` whether or not it exists yet. The contract first tries to create it with
+    /// `public_key` as its full access key; if that fails because the account already exists, the drop
+    /// is transferred to it instead. If the transfer fails too, the drop is restored.
+    /// Implicit accounts are created by the transfer itself, so they are claimed into with `claim`, and names
+    /// this contract cannot create are only transferred to. Reserved names are rejected.
+    pub fn ensure_account_and_claim(
         &mut self,
-        new_account_id: AccountId,
-        new_public_key: PublicKey,
+        account_id: AccountId,
+        public_key: PublicKey,
+        password: Option<String>,
     ) -> Promise {
+        if is_implicit_account(&account_id) {
+            return self.claim(account_id, password, None);
+        }
+        self.assert_not_paused();
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Claim only can come from this account"
+        );
         assert!(
-            env::is_valid_account_id(new_account_id.as_bytes()),
+            env::is_valid_account_id(account_id.as_bytes()),
             "Invalid account id"
         );
-        let amount = env::attached_deposit();
-        Promise::new(new_account_id)
+        assert!(!self.is_reserved(&account_id), "Account name is reserved");
+        if !self.can_create(&account_id) {
+            return self.smart_claim(account_id, true, None, password);
+        }
+        let drop = self.internal_take_drop_in_flight(password);
+        let callback_gas = claim_callback_gas(&drop);
+        Promise::new(account_id.clone())
             .create_account()
-            .add_full_access_key(new_public_key.into())
-            .transfer(amount)
+            .add_full_access_key(public_key)
+            .transfer(drop.balance)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(ON_CREATE_ACCOUNT_CALLBACK_GAS + callback_gas)
+                    .on_ensure_account(account_id, drop, callback_gas)
+            )
+    }
+
+    /// Callback after `ensure_account_and_claim` tried to create the account. Finishes the claim if the
+    /// account was created, otherwise transfers the drop to the existing account.
+    pub fn on_ensure_account(
+        &mut self,
+        #[serializer(borsh)] account_id: AccountId,
+        #[serializer(borsh)] drop: DropInfo,
+        #[serializer(borsh)] callback_gas: Gas,
+    ) -> PromiseOrValue<bool> {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        if is_promise_success() {
+            return PromiseOrValue::Value(self.on_account_created_and_claimed(account_id, drop, 0));
+        }
+        // The balance sent with the failed creation is refunded to this contract
+        Promise::new(account_id.clone())
+            .transfer(drop.balance)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(callback_gas)
+                    .on_account_created_and_claimed(account_id, drop, 0)
+            )
+            .into()
+    }
+
+    /// Claims into `account_id`, creating it first unless the claimer says it already exists.
+    /// `public_key` becomes the full access key of the new account and is required when `account_exists`
+    /// is false. On either path the drop is restored if the claim fails.
+    /// Implicit accounts are created by the transfer itself, so they are claimed into with `claim`.
+    pub fn smart_claim(
+        &mut self,
+        account_id: AccountId,
+        account_exists: bool,
+        public_key: Option<PublicKey>,
+        password: Option<String>,
+    ) -> Promise {
+        if is_implicit_account(&account_id) {
+            return self.claim(account_id, password, None);
+        }
+        if !account_exists {
+            let public_key = public_key.expect("Public key is required to create the account");
+            return self
+                .create_account_and_claim(account_id, public_key, None, password)
+                .unwrap_or_else(|err| panic!("{}", err.as_ref()));
+        }
+        self.assert_not_paused();
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Claim only can come from this account"
+        );
+        assert!(
+            env::is_valid_account_id(account_id.as_bytes()),
+            "Invalid account id"
+        );
+        let drop = self.internal_take_drop_in_flight(password);
+        let callback_gas = claim_callback_gas(&drop);
+        Promise::new(account_id.clone())
+            .transfer(drop.balance)
             .then(
                 Self::ext(env::current_account_id())
-                    .with_static_gas(ON_CREATE_ACCOUNT_CALLBACK_GAS)
-                    .on_account_created(
-                        env::predecessor_account_id(),
-                        amount.into()
-                    )
+                    .with_static_gas(callback_gas)
+                    .on_account_created_and_claimed(account_id, drop, 0)
             )
     }
 
     /// Create new account without linkdrop and deposit passed funds (used for creating sub accounts directly).
     #[payable]
-    pub fn create_account_advanced(
+    pub fn create_account(
         &mut self,
         new_account_id: AccountId,
-        options: CreateAccountOptions,
+        new_public_key: PublicKey,
     ) -> Promise {
-        let is_some_option = options.contract_bytes_base64.is_some() || options.contract_bytes.is_some() || options.full_access_keys.is_some() || options.limited_access_keys.is_some();
-        assert!(is_some_option, "Cannot create account with no options. Please specify either contract bytes, full access keys, or limited access keys.");
+        self.assert_not_paused();
+        assert!(
+            env::is_valid_account_id(new_account_id.as_bytes()),
+            "Invalid account id"
+        );
+        assert!(self.can_create(&new_account_id), "Cannot create this account name");
+        assert!(!self.is_reserved(&new_account_id), "Account name is reserved");
+        internal_create_account(FailedCreation {
+            new_account_id,
+            public_key: new_public_key,
+            amount: env::attached_deposit().into(),
+            predecessor_account_id: env::predecessor_account_id(),
+            retries: 0,
+        })
+    }
 
-        let is_conflict_contract_bytes = options.contract_bytes_base64.is_some() && options.contract_bytes.is_some();
-        assert!(!is_conflict_contract_bytes, "Cannot give contract bytes and base64 contract byte string at the same time.");
+    /// Same as `create_account`, but adds each of `public_keys` as a full access key of the new account.
+    /// At most MAX_KEYS_PER_ACCOUNT keys can be given. Failed creations are refunded, never retried.
+    #[payable]
+    pub fn create_account_multikey(&mut self, new_account_id: AccountId, public_keys: Vec<PublicKey>) -> Promise {
+        self.assert_not_paused();
+        assert!(
+            env::is_valid_account_id(new_account_id.as_bytes()),
+            "Invalid account id"
+        );
+        assert!(self.can_create(&new_account_id), "Cannot create this account name");
+        assert!(!self.is_reserved(&new_account_id), "Account name is reserved");
+        assert!(!public_keys.is_empty(), "At least one public key is required");
+        assert!(public_keys.len() <= MAX_KEYS_PER_ACCOUNT, "{}", LinkdropError::TooManyKeys.as_ref());
+        let mut seen = HashSet::new();
+        assert!(public_keys.iter().all(|key| seen.insert(key)), "{}", LinkdropError::DuplicateKey.as_ref());
 
         let amount = env::attached_deposit();
-
-        // Initiate a new promise on the new account we're creating and transfer it any attached deposit
-        let mut promise = Promise::new(new_account_id).create_account().transfer(amount);
-        
-        // If there are any full access keys in the options, loop through and add them to the promise
-        if let Some(full_access_keys) = options.full_access_keys {
+        let mut promise = Promise::new(new_account_id.clone()).create_account();
+        for public_key in public_keys {
+            promise = promise.add_full_access_key(public_key);
+        }
+        promise.transfer(amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                .on_account_created(
+                    new_account_id,
+                    env::predecessor_account_id(),
+                    amount.into(),
+                    None,
+                    0,
+                    None
+                )
+        )
+    }
+
+    /// Same as `create_account`, but the new account is funded from the contract's unallocated balance with
+    /// just enough to pay for its storage. If the creation fails, the funds go back to the contract.
+    /// Only callable by the owner.
+    pub fn create_account_free(&mut self, new_account_id: AccountId, new_public_key: PublicKey) -> Promise {
+        self.assert_owner();
+        self.assert_not_paused();
+        assert!(
+            env::is_valid_account_id(new_account_id.as_bytes()),
+            "Invalid account id"
+        );
+        assert!(self.can_create(&new_account_id), "Cannot create this account name");
+        assert!(!self.is_reserved(&new_account_id), "Account name is reserved");
+        let amount = free_account_amount();
+        assert!(self.unallocated_balance() >= amount, "Not enough free balance to fund the account");
+        internal_create_account(FailedCreation {
+            new_account_id,
+            public_key: new_public_key,
+            amount: amount.into(),
+            predecessor_account_id: env::current_account_id(),
+            retries: 0,
+        })
+    }
+
+    /// Attempts again up to `limit` failed `create_account` calls from the queue, most recent first.
+    /// Returns the number of attempts started. Only callable by the owner or an admin.
+    pub fn retry_failed_creations(&mut self, limit: u64) -> u64 {
+        self.assert_not_paused();
+        let predecessor = env::predecessor_account_id();
+        assert!(
+            predecessor == self.owner_id || self.admins.contains(&predecessor),
+            "Only the owner or an admin can retry failed creations"
+        );
+        let mut retried = 0;
+        while retried < limit {
+            let mut entry = match self.retry_queue.pop() {
+                Some(entry) => entry,
+                None => break,
+            };
+            self.retry_locked = self.retry_locked.saturating_sub(entry.amount.0);
+            entry.retries += 1;
+            internal_create_account(entry);
+            retried += 1;
+        }
+        retried
+    }
+
+    /// Takes the failed `create_account` at `index` of the retry queue off it and refunds its deposit,
+    /// e.g. when the owner does not retry it. Only callable by the caller of the failed `create_account`.
+    pub fn refund_failed_creation(&mut self, index: u64) -> Promise {
+        self.assert_not_paused();
+        let entry = self.retry_queue.get(index).expect("No failed creation at the given index");
+        assert_eq!(
+            entry.predecessor_account_id,
+            env::predecessor_account_id(),
+            "Only the caller of the failed creation can refund it"
+        );
+        self.retry_queue.swap_remove(index);
+        self.retry_locked = self.retry_locked.saturating_sub(entry.amount.0);
+        emit(EventKind::Refund(vec![RefundLog {
+            account_id: entry.new_account_id,
+            amount: entry.amount,
+            reason: REFUND_REASON_CREATION_FAILED.to_string(),
+        }]));
+        Promise::new(entry.predecessor_account_id).transfer(entry.amount.0)
+    }
+
+    /// Sets how many times a failed `create_account` is queued for `retry_failed_creations` before its
+    /// deposit is refunded. Zero refunds right away. Only callable by the owner.
+    pub fn set_max_creation_retries(&mut self, max_retries: u32) {
+        self.assert_owner();
+        self.max_creation_retries = max_retries;
+    }
+
+    /// Returns the failed `create_account` calls waiting to be retried.
+    pub fn get_retry_queue(&self, from_index: u64, limit: u64) -> Vec<FailedCreation> {
+        (from_index..std::cmp::min(from_index.saturating_add(limit), self.retry_queue.len()))
+            .filter_map(|index| self.retry_queue.get(index))
+            .collect()
+    }
+
+    /// Create new account without linkdrop and deposit passed funds (used for creating sub accounts directly).
+    /// Only callable by the owner or an admin.
+    #[payable]
+    pub fn create_account_advanced(
+        &mut self,
+        new_account_id: AccountId,
+        options: CreateAccountOptions,
+    ) -> Promise {
+        self.assert_not_paused();
+        let predecessor = env::predecessor_account_id();
+        assert!(
+            predecessor == self.owner_id || self.admins.contains(&predecessor),
+            "Only the owner or an admin can create advanced accounts"
+        );
+        assert!(self.can_create(&new_account_id), "Cannot create this account name");
+        assert!(!self.is_reserved(&new_account_id), "Account name is reserved");
+        self.check_create_options(&options).unwrap_or_else(|err| panic!("{}", err.as_ref()));
+
+        // Keys given with a list of method names are joined into the comma-separated form
+        let mut options = options;
+        if let Some(keys) = options.limited_access_keys_v2.take() {
+            options.limited_access_keys
+                .get_or_insert_with(Vec::new)
+                .extend(keys.into_iter().map(LimitedAccessKey::from));
+        }
+        let callback_gas = options.callback_gas.unwrap_or(ON_CREATE_ACCOUNT_CALLBACK_GAS);
+        let amount = env::attached_deposit();
+
+        // The staked balance cannot pay for the new account's storage
+        if let Some((_, stake_amount)) = options.stake.as_ref() {
+            let reserved = new_account_storage_reserve(&options);
+            assert!(
+                stake_amount.0 <= amount.saturating_sub(reserved),
+                "Stake exceeds the attached deposit minus the storage reserve"
+            );
+        }
+
+        // Summarize what is about to be done for indexers
+        let contract_bytes = options.contract_bytes();
+        let log = CreateAccountAdvancedLog {
+            account_id: new_account_id.clone(),
+            full_access_keys: options.full_access_keys.as_ref().map_or(0, Vec::len) as u32,
+            limited_access_keys: options.limited_access_keys.as_ref().map_or(0, Vec::len) as u32,
+            contract_hash: contract_bytes.map(|bytes| env::sha256_array(bytes).into()),
+            amount: amount.into(),
+        };
+
+        // Initiate a new promise on the new account we're creating and transfer it any attached deposit
+        let mut promise = Promise::new(new_account_id.clone()).create_account().transfer(amount);
+        
+        // If there are any full access keys in the options, loop through and add them to the promise
+        if let Some(full_access_keys) = options.full_access_keys {
             for key in full_access_keys {
                 promise = promise.add_full_access_key(key.clone());
             }
@@ -193,70 +1219,859 @@ impl LinkDrop {
             promise = promise.deploy_contract(bytes.0);
         };
 
-        // Callback if anything went wrong, refund the predecessor for their attached deposit
+        // If a stake is requested, the new account stakes part of its balance with the given validator key
+        if let Some((public_key, stake_amount)) = options.stake {
+            promise = promise.stake(stake_amount.0, public_key);
+        };
+
+        // Callback emits the summary once the account exists, or refunds the predecessor if anything went wrong
         promise.then(
             Self::ext(env::current_account_id())
-                .with_static_gas(ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                .with_static_gas(callback_gas)
                 .on_account_created(
+                    new_account_id,
                     env::predecessor_account_id(),
-                    amount.into()
+                    amount.into(),
+                    None,
+                    0,
+                    Some(AdvancedCreation { log, key_labels: options.key_labels })
                 )
         )
     }
 
+    /// Runs the checks `create_account_advanced` applies to its options, without creating anything.
+    /// Checks that depend on the attached deposit, such as the stake amount, are not covered.
+    #[handle_result]
+    pub fn validate_create_account_options(&self, options: CreateAccountOptions) -> Result<(), LinkdropError> {
+        self.check_create_options(&options)
+    }
+
+    /// Same as `validate_create_account_options`, but returns the message `create_account_advanced` would
+    /// panic with.
+    #[handle_result]
+    pub fn validate_options(&self, options: CreateAccountOptions) -> Result<(), String> {
+        self.validate_create_account_options(options).map_err(|err| err.as_ref().to_string())
+    }
+
+    /// Returns the smallest deposit `create_account_advanced` needs for the given options: the storage of the
+    /// new account, its access keys and its contract, plus the stake, if any. Mirrors the reserve the call keeps.
+    pub fn estimate_required_deposit(&self, options: CreateAccountOptions) -> U128 {
+        let stake_amount = options.stake.as_ref().map_or(0, |(_, amount)| amount.0);
+        new_account_storage_reserve(&options).saturating_add(stake_amount).into()
+    }
+
+    /// Lists the reasons `create_account_advanced` would likely fail with `deposit` attached, without creating
+    /// anything. Empty when none were found. This is advisory only: whether the account already exists
+    /// cannot be checked from the contract.
+    pub fn preview_create_account_advanced(
+        &self,
+        new_account_id: AccountId,
+        options: CreateAccountOptions,
+        deposit: U128,
+    ) -> Vec<PreviewWarning> {
+        let mut warnings = Vec::new();
+        if !self.can_create(&new_account_id) {
+            warnings.push(PreviewWarning::CannotCreateAccount);
+        }
+        if self.is_reserved(&new_account_id) {
+            warnings.push(PreviewWarning::ReservedName);
+        }
+        if let Err(err) = self.check_create_options(&options) {
+            warnings.push(PreviewWarning::InvalidOptions(err.as_ref().to_string()));
+        }
+        let required = self.estimate_required_deposit(options);
+        if deposit.0 < required.0 {
+            warnings.push(PreviewWarning::InsufficientDeposit { required });
+        }
+        warnings
+    }
+
     /// Callback after executing `create_account` or `create_account_advanced`.
-    pub fn on_account_created(&mut self, predecessor_account_id: AccountId, amount: U128) -> bool {
+    /// A failed `create_account`, which passes its `public_key`, is queued for `retry_failed_creations`
+    /// until it has been retried `max_creation_retries` times. Anything else that failed is refunded.
+    /// The summary of `create_account_advanced` is only emitted, and its key labels only recorded,
+    /// once the account exists.
+    pub fn on_account_created(
+        &mut self,
+        new_account_id: AccountId,
+        predecessor_account_id: AccountId,
+        amount: U128,
+        public_key: Option<PublicKey>,
+        retries: u32,
+        advanced: Option<AdvancedCreation>,
+    ) -> bool {
         assert_eq!(
             env::predecessor_account_id(),
             env::current_account_id(),
             "Callback can only be called from the contract"
         );
         let creation_succeeded = is_promise_success();
-        if !creation_succeeded {
-            // In case of failure, send funds back.
-            Promise::new(predecessor_account_id).transfer(amount.into());
+        if creation_succeeded {
+            if let Some(advanced) = advanced {
+                for (key, label) in advanced.key_labels.into_iter().flatten() {
+                    self.key_labels.insert(&(new_account_id.clone(), key), &label);
+                }
+                emit(EventKind::CreateAccountAdvanced(vec![advanced.log]));
+            }
+            return true;
         }
-        creation_succeeded
+        match public_key {
+            Some(public_key) if retries < self.max_creation_retries => {
+                // Keep the funds for another attempt
+                self.retry_locked = self
+                    .retry_locked
+                    .checked_add(amount.0)
+                    .expect("Total retry balance overflow");
+                self.retry_queue.push(&FailedCreation {
+                    new_account_id,
+                    public_key,
+                    amount,
+                    predecessor_account_id,
+                    retries,
+                });
+            }
+            _ => {
+                // In case of failure, send funds back.
+                Promise::new(predecessor_account_id).transfer(amount.into());
+                emit(EventKind::Refund(vec![RefundLog {
+                    account_id: new_account_id,
+                    amount,
+                    reason: REFUND_REASON_CREATION_FAILED.to_string(),
+                }]));
+            }
+        }
+        false
     }
 
     /// Callback after execution `create_account_and_claim`.
-    pub fn on_account_created_and_claimed(&mut self, amount: U128) -> bool {
+    /// On success the NFT held by the drop, if any, is sent to the new account. On failure the drop is paid out the same way
+    /// to its fallback account if it has one, otherwise it is restored and the NFT stays with the contract.
+    /// The access key is only deleted here, once the drop is paid out: the account creation batch either
+    /// applies in full or not at all, so on failure the key is still in place to claim the restored drop.
+    /// The `sponsored` amount sent along comes back with a failed batch and goes back to the reserve.
+    pub fn on_account_created_and_claimed(
+        &mut self,
+        #[serializer(borsh)] new_account_id: AccountId,
+        #[serializer(borsh)] drop: DropInfo,
+        #[serializer(borsh)] sponsored: Balance,
+    ) -> bool {
         assert_eq!(
             env::predecessor_account_id(),
             env::current_account_id(),
             "Callback can only be called from the contract"
         );
+        self.claiming.remove(&env::signer_account_pk());
         let creation_succeeded = is_promise_success();
-        if creation_succeeded {
-            Promise::new(env::current_account_id()).delete_key(env::signer_account_pk());
+        if !creation_succeeded {
+            self.sponsorship_reserve = self
+                .sponsorship_reserve
+                .checked_add(sponsored)
+                .expect("Sponsorship reserve overflow");
+        }
+        let recipient = if creation_succeeded {
+            new_account_id
+        } else if let Some(fallback_account) = drop.fallback_account.clone() {
+            // The balance came back with the failed batch, pay it to the fallback account instead.
+            Promise::new(fallback_account.clone()).transfer(drop.balance);
+            fallback_account
         } else {
-            // In case of failure, put the amount back.
-            self.accounts
-                .insert(&env::signer_account_pk(), &amount.into());
+            // In case of failure, put the drop back.
+            emit(EventKind::Refund(vec![RefundLog {
+                account_id: new_account_id,
+                amount: drop.balance.into(),
+                reason: REFUND_REASON_CREATION_FAILED.to_string(),
+            }]));
+            self.internal_restore_drop(&env::signer_account_pk(), drop);
+            return false;
+        };
+        Promise::new(env::current_account_id()).delete_key(env::signer_account_pk());
+        self.record_claim(drop.balance);
+        self.update_funder_stats(drop.funder_id.as_ref(), |stats| stats.claimed += 1);
+        emit(EventKind::Claim(vec![ClaimLog {
+            account_id: recipient.clone(),
+            amount: drop.balance.into(),
+            memo: None,
+            message: drop.claim_message,
+        }]));
+        if let Some(nft) = drop.nft {
+            transfer_nft(nft, recipient, None);
         }
         creation_succeeded
     }
 
     /// Returns the balance associated with given key.
-    pub fn get_key_balance(&self, key: PublicKey) -> U128 {
-        self.accounts.get(&key.into()).expect("Key is missing").into()
+    #[handle_result]
+    pub fn get_key_balance(&self, key: PublicKey) -> Result<U128, LinkdropError> {
+        self.accounts.get(&key).map(|drop| drop.balance.into()).ok_or(LinkdropError::KeyMissing)
+    }
+
+    /// Returns the yoctoNEAR$ amount the next claim with the key sends to the recipient. Fees are taken when the
+    /// drop is sent, so this is the drop balance, except for
+    /// random drops: their claims pay a random amount, and this returns the most it can be.
+    #[handle_result]
+    pub fn get_net_claim_amount(&self, public_key: PublicKey) -> Result<U128, LinkdropError> {
+        let drop = self.accounts.get(&public_key).ok_or(LinkdropError::KeyMissing)?;
+        Ok(match drop.random {
+            Some(random) => random.max.min(drop.balance).into(),
+            None => drop.balance.into(),
+        })
     }
 
     /// Returns information associated with a given key.
     /// Part of the linkdrop NEP
     #[handle_result]
-    pub fn get_key_information(&self, key: PublicKey) -> Result<KeyInfo, &'static str> {
-        match self.accounts.get(&key) {
-            Some(balance) => Ok(KeyInfo { balance: U128(balance) }),
-            None => Err("Key is missing"),
+    pub fn get_key_information(&self, key: PublicKey) -> Result<KeyInfo, LinkdropError> {
+        self.accounts.get(&key).map(KeyInfo::from).ok_or(LinkdropError::KeyMissing)
+    }
+
+    /// Returns the methods the access key of the drop can call, or `None` if the key has no drop.
+    pub fn get_key_methods(&self, public_key: PublicKey) -> Option<Vec<String>> {
+        self.accounts
+            .get(&public_key)
+            .map(|drop| drop.key_method_names().split(',').map(String::from).collect())
+    }
+
+    /// Whether the key has a drop that can be claimed now, i.e. it exists and the contract is not paused.
+    /// Password and pending commitment checks are left to the claim itself.
+    pub fn is_claimable(&self, public_key: PublicKey) -> bool {
+        !self.paused && self.accounts.get(&public_key).is_some()
+    }
+
+    /// Returns information for each of the given keys, in the same order.
+    /// Missing keys are returned as `None` rather than causing a panic.
+    pub fn get_key_information_batch(&self, keys: Vec<PublicKey>) -> Vec<Option<KeyInfo>> {
+        keys.iter()
+            .map(|key| self.accounts.get(key).map(KeyInfo::from))
+            .collect()
+    }
+
+    /// Returns the label given to `public_key` when `create_account_advanced` added it to `account_id`.
+    pub fn get_key_label(&self, account_id: AccountId, public_key: PublicKey) -> Option<String> {
+        self.key_labels.get(&(account_id, public_key))
+    }
+
+    /// Allows the given account to call `create_account_advanced`. Only callable by the owner.
+    #[handle_result]
+    pub fn add_admin(&mut self, account_id: AccountId) -> Result<bool, LinkdropError> {
+        self.check_owner()?;
+        Ok(self.admins.insert(&account_id))
+    }
+
+    /// Revokes admin rights from the given account. Only callable by the owner.
+    #[handle_result]
+    pub fn remove_admin(&mut self, account_id: AccountId) -> Result<bool, LinkdropError> {
+        self.check_owner()?;
+        Ok(self.admins.remove(&account_id))
+    }
+
+    /// Reserves account names matching `pattern`, which is either `prefix*`, `*suffix` or an exact name,
+    /// without the suffix of this contract. Only callable by the owner.
+    pub fn add_reserved_pattern(&mut self, pattern: String) -> bool {
+        self.assert_owner();
+        assert!(!pattern.trim_matches('*').is_empty(), "Pattern cannot be empty");
+        self.reserved_patterns.insert(&pattern)
+    }
+
+    /// Releases account names reserved by `add_reserved_pattern`. Only callable by the owner.
+    pub fn remove_reserved_pattern(&mut self, pattern: String) -> bool {
+        self.assert_owner();
+        self.reserved_patterns.remove(&pattern)
+    }
+
+    /// Sends the balance not backing any drop to `beneficiary`, e.g. dust and leftovers of failed refunds.
+    /// Keeps the storage staking requirement, the balances of live drops, the access key allowance of
+    /// each live drop, which pays for its claim, the fees left for `withdraw_fees`, the deposits of failed
+    /// creations waiting to be retried, the unused NEP-145 storage balances and the sponsorship reserve.
+    /// What a claimed or cancelled drop's key left of its allowance is not refunded to the funder, so it
+    /// is swept too. Only callable by the owner.
+    pub fn sweep_unallocated(&mut self, beneficiary: AccountId) -> Promise {
+        self.assert_owner();
+        let surplus = self.unallocated_balance();
+        assert!(surplus > 0, "Nothing to sweep");
+        Promise::new(beneficiary).transfer(surplus)
+    }
+
+    /// Sets the share of each deposit funding a drop kept as a fee, in basis points, at most MAX_FEE_BASIS_POINTS.
+    /// Only callable by the owner.
+    pub fn set_fee_basis_points(&mut self, fee_basis_points: u16) {
+        self.assert_owner();
+        assert!(
+            fee_basis_points <= MAX_FEE_BASIS_POINTS,
+            "Fee exceeds MAX_FEE_BASIS_POINTS"
+        );
+        self.fee_basis_points = fee_basis_points;
+    }
+
+    /// Adds the attached deposit to the reserve sponsoring accounts created by `create_account_and_claim`.
+    #[payable]
+    pub fn fund_sponsorship_reserve(&mut self) -> U128 {
+        assert!(env::attached_deposit() > 0, "Deposit must be greater than zero");
+        self.sponsorship_reserve = self
+            .sponsorship_reserve
+            .checked_add(env::attached_deposit())
+            .expect("Sponsorship reserve overflow");
+        self.sponsorship_reserve.into()
+    }
+
+    /// Sets the amount each account created by `create_account_and_claim` gets from the sponsorship
+    /// reserve, e.g. 0.01 NEAR. Zero disables sponsorship. Only callable by the owner.
+    pub fn set_sponsorship_amount(&mut self, amount: U128) {
+        self.assert_owner();
+        self.sponsorship_amount = amount.0;
+    }
+
+    /// Sends what is left of the sponsorship reserve to `beneficiary`. Only callable by the owner.
+    pub fn withdraw_sponsorship_reserve(&mut self, beneficiary: AccountId) -> Promise {
+        self.assert_owner();
+        let reserve = std::mem::take(&mut self.sponsorship_reserve);
+        assert!(reserve > 0, "Sponsorship reserve is empty");
+        Promise::new(beneficiary).transfer(reserve)
+    }
+
+    /// Returns the yoctoNEAR$ left to sponsor new accounts.
+    pub fn get_sponsorship_reserve(&self) -> U128 {
+        self.sponsorship_reserve.into()
+    }
+
+    /// Sends the fees collected so far to `beneficiary`. Only callable by the owner.
+    pub fn withdraw_fees(&mut self, beneficiary: AccountId) -> Promise {
+        self.assert_owner();
+        let fees = std::mem::take(&mut self.owner_fees_collected);
+        assert!(fees > 0, "No fees to withdraw");
+        Promise::new(beneficiary).transfer(fees)
+    }
+
+    /// Sets the minimum time in nanoseconds between two `claim_amount` withdrawals from the same drop.
+    /// Only callable by the owner.
+    pub fn set_claim_cooldown(&mut self, cooldown_ns: U64) {
+        self.assert_owner();
+        self.claim_cooldown_ns = cooldown_ns.0;
+    }
+
+    /// Sets how many claims of the same drop a single account can receive. Zero removes the limit.
+    /// Claims made while there was no limit are not counted. Only callable by the owner.
+    pub fn set_max_claims_per_account(&mut self, max_claims: u32) {
+        self.assert_owner();
+        self.max_claims_per_account = max_claims;
+    }
+
+    /// Sets the comma-separated methods the access keys of new drops can call, e.g. only
+    /// `claim,create_account_and_claim`. Existing drops keep theirs. Only callable by the owner.
+    pub fn set_default_claim_methods(&mut self, methods: String) {
+        self.assert_owner();
+        assert_method_names(&methods);
+        self.default_claim_methods = methods;
+    }
+
+    /// Sets the largest contract, in bytes, `create_account_advanced` accepts to deploy.
+    /// Only callable by the owner.
+    pub fn set_max_contract_bytes(&mut self, max_bytes: U64) {
+        self.assert_owner();
+        self.max_contract_bytes = max_bytes.0;
+    }
+
+    /// Sets the allowance given to the access keys of new drops. Existing drops keep theirs.
+    /// Zero gives new keys an unlimited allowance. Only callable by the owner.
+    pub fn set_access_key_allowance(&mut self, amount: U128) {
+        self.assert_owner();
+        self.access_key_allowance = amount.0;
+    }
+
+    /// Halts or resumes sends, claims and account creation. Views stay available. Only callable by the owner.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.assert_owner();
+        self.paused = paused;
+    }
+
+    /// Returns how many drops the funder created and how many of them were claimed or reclaimed.
+    pub fn get_funder_stats(&self, account_id: AccountId) -> Option<FunderStats> {
+        self.funder_stats.get(&account_id)
+    }
+
+    /// Returns the keys and balances of the outstanding drops of `funder_id`, paginated.
+    pub fn get_drops_by_funder(&self, funder_id: AccountId, from_index: u64, limit: u64) -> Vec<(PublicKey, U128)> {
+        let drops = match self.drops_by_funder.get(&funder_id) {
+            Some(drops) => drops,
+            None => return vec![],
+        };
+        let keys = drops.as_vector();
+        (from_index..std::cmp::min(from_index.saturating_add(limit), keys.len()))
+            .filter_map(|index| keys.get(index))
+            .map(|pk| {
+                let balance = self.accounts.get(&pk).map_or(0, |drop| drop.balance);
+                (pk, balance.into())
+            })
+            .collect()
+    }
+
+    /// Returns all the admins.
+    pub fn get_admins(&self) -> Vec<AccountId> {
+        self.admins.to_vec()
+    }
+
+    /// Returns the sum of the balances of all outstanding drops.
+    pub fn get_total_locked(&self) -> U128 {
+        self.total_locked.into()
+    }
+
+    /// Returns how the contract balance is split between storage, outstanding drops and free funds.
+    /// Storage released by claimed drops flows back into the available balance.
+    pub fn get_balance_breakdown(&self) -> BalanceBreakdown {
+        let total = env::account_balance();
+        let storage_staked = Balance::from(env::storage_usage()) * env::storage_byte_cost();
+        let locked = self.total_locked;
+        BalanceBreakdown {
+            total: total.into(),
+            storage_staked: storage_staked.into(),
+            locked: locked.into(),
+            available: total.saturating_sub(storage_staked).saturating_sub(locked).into(),
+        }
+    }
+
+    /// Returns the counters and balances a watchdog needs in one call. Reads no collection entries.
+    pub fn get_monitoring_snapshot(&self) -> Snapshot {
+        Snapshot {
+            drops: self.accounts.len(),
+            total_locked: self.total_locked.into(),
+            available: self.get_balance_breakdown().available,
+            claimed_count: self.claimed_count,
+            claimed_amount: self.claimed_amount.into(),
+            paused: self.paused,
+            owner_id: self.owner_id.clone(),
+        }
+    }
+
+    /// Returns the drop counters of the whole contract in one call, e.g. for a dashboard.
+    pub fn get_stats(&self) -> ContractStats {
+        ContractStats {
+            total_drops: self.accounts.len(),
+            total_locked: self.total_locked.into(),
+            total_claimed: self.claimed_count,
+            total_reclaimed: self.reclaimed_count,
+            paused: self.paused,
+        }
+    }
+
+    /// Returns the version of the deployed code and the settings it was built with.
+    pub fn contract_metadata(&self) -> ContractMetadata {
+        ContractMetadata {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            standards: vec!["linkdrop-1.0.0".to_string()],
+            access_key_allowance: self.access_key_allowance.into(),
+            default_claim_methods: self.default_claim_methods.clone(),
+        }
+    }
+}
+
+impl LinkDrop {
+    /// Balance of the contract not backing anything: see `sweep_unallocated`.
+    fn unallocated_balance(&self) -> Balance {
+        self.get_balance_breakdown()
+            .available
+            .0
+            .saturating_sub(self.locked_allowances)
+            .saturating_sub(self.owner_fees_collected)
+            .saturating_sub(self.retry_locked)
+            .saturating_sub(self.storage_available_total)
+            .saturating_sub(self.sponsorship_reserve)
+    }
+
+    /// Takes `sponsorship_amount` out of the sponsorship reserve for a new account. Returns zero while
+    /// sponsorship is disabled or the reserve cannot cover it.
+    fn take_sponsorship(&mut self) -> Balance {
+        let amount = self.sponsorship_amount;
+        if amount == 0 || self.sponsorship_reserve < amount {
+            return 0;
+        }
+        self.sponsorship_reserve -= amount;
+        amount
+    }
+
+    /// Panics if the contract is paused.
+    fn assert_not_paused(&self) {
+        assert!(!self.paused, "Contract is paused");
+    }
+
+    /// Panics if the predecessor is not the owner.
+    fn assert_owner(&self) {
+        self.check_owner().unwrap_or_else(|err| panic!("{}", err.as_ref()));
+    }
+
+    /// Returns `Unauthorized` if the predecessor is not the owner.
+    fn check_owner(&self) -> Result<(), LinkdropError> {
+        if env::predecessor_account_id() != self.owner_id {
+            return Err(LinkdropError::Unauthorized);
+        }
+        Ok(())
+    }
+
+    /// Whether this contract is able to create the given account: either a direct subaccount
+    /// of the contract, or a top-level account when the contract is the registrar.
+    fn can_create(&self, new_account_id: &AccountId) -> bool {
+        let current_account_id = env::current_account_id();
+        let suffix = format!(".{}", current_account_id);
+        match new_account_id.as_str().strip_suffix(&suffix) {
+            Some(name) => !name.contains('.'),
+            None => {
+                current_account_id.as_str() == REGISTRAR_ACCOUNT_ID
+                    && !new_account_id.as_str().contains('.')
+            }
+        }
+    }
+
+    /// `validate_create_options`, plus the checks that depend on the contract's settings.
+    fn check_create_options(&self, options: &CreateAccountOptions) -> Result<(), LinkdropError> {
+        validate_create_options(options)?;
+        if matches!(options.contract_bytes(), Some(bytes) if bytes.len() as u64 > self.max_contract_bytes) {
+            return Err(LinkdropError::ContractTooLarge);
+        }
+        Ok(())
+    }
+
+    /// Whether the name of the account, without the suffix of this contract, matches a reserved pattern.
+    fn is_reserved(&self, account_id: &AccountId) -> bool {
+        let suffix = format!(".{}", env::current_account_id());
+        let name = account_id.as_str().strip_suffix(&suffix).unwrap_or_else(|| account_id.as_str());
+        self.reserved_patterns.iter().any(|pattern| {
+            if let Some(prefix) = pattern.strip_suffix('*') {
+                name.starts_with(prefix)
+            } else if let Some(suffix) = pattern.strip_prefix('*') {
+                name.ends_with(suffix)
+            } else {
+                name == pattern
+            }
+        })
+    }
+
+    /// Registers `drop` for the given key, funded out of the attached deposit, and adds its access key.
+    fn internal_send(&mut self, public_key: PublicKey, mut drop: DropInfo) -> Promise {
+        self.assert_not_paused();
+        let allowance = self.access_key_allowance;
+        drop.balance = self.take_drop_balance(allowance);
+        drop.funder_id = Some(env::predecessor_account_id());
+        drop.allowance = allowance;
+        drop.method_names = drop.method_names.or_else(|| self.drop_method_names());
+        let method_names = drop.key_method_names().to_string();
+        let initial_storage = env::storage_usage();
+        self.internal_add_to_drop(&public_key, drop);
+        self.charge_storage(&public_key, &env::predecessor_account_id(), initial_storage);
+        // An allowance of zero gives the key an unlimited allowance
+        Promise::new(env::current_account_id()).add_access_key(
+            public_key,
+            allowance,
+            env::current_account_id(),
+            method_names,
+        )
+    }
+
+    /// Takes the owner fee out of `amount`, which funds a drop, and returns what is left of it.
+    /// Every path that adds to a drop's balance goes through here, so splitting a send into a small
+    /// `send` and a large `top_up` still pays the fee.
+    fn take_fee(&mut self, amount: Balance) -> Balance {
+        let fee = amount * u128::from(self.fee_basis_points) / 10_000;
+        self.owner_fees_collected = self
+            .owner_fees_collected
+            .checked_add(fee)
+            .expect("Total fees overflow");
+        amount - fee
+    }
+
+    /// Takes the owner fee out of the attached deposit and returns the drop balance left once `allowance`
+    /// is set aside for the access key.
+    fn take_drop_balance(&mut self, allowance: Balance) -> Balance {
+        let net_deposit = self.take_fee(env::attached_deposit());
+        // The deposit must leave a non-zero balance once the allowance and the fee are taken out
+        assert!(net_deposit > allowance, "Deposit must cover the access key allowance and the fee");
+        net_deposit - allowance
+    }
+
+    /// Adds `drop` to the drop registered under `pk`, creating it if needed.
+    /// Balances are summed, the original funder is kept and any other field set on `drop`
+    /// replaces the existing one. Anyone can add to the balance of an existing drop, but only its
+    /// funder can change its settings.
+    fn internal_add_to_drop(&mut self, pk: &PublicKey, drop: DropInfo) {
+        let amount = drop.balance;
+        let drop = match self.accounts.get(pk) {
+            Some(existing) => {
+                assert!(
+                    !drop.has_funder_settings() || existing.funder_id.as_ref() == Some(&env::predecessor_account_id()),
+                    "Only the funder of the drop can change its settings"
+                );
+                DropInfo {
+                    balance: existing.balance.checked_add(amount).expect("Drop balance overflow"),
+                    funder_id: existing.funder_id.or(drop.funder_id),
+                    password_hash: drop.password_hash.or(existing.password_hash),
+                    metadata: drop.metadata.or(existing.metadata),
+                    nft: drop.nft.or(existing.nft),
+                    new_account_limited_keys: drop.new_account_limited_keys.or(existing.new_account_limited_keys),
+                    commitment: existing.commitment.or(drop.commitment),
+                    created_at: existing.created_at,
+                    allowance: existing.allowance,
+                    claim_message: drop.claim_message.or(existing.claim_message),
+                    last_claim_at: existing.last_claim_at,
+                    fallback_account: drop.fallback_account.or(existing.fallback_account),
+                    random: existing.random,
+                    method_names: existing.method_names,
+                    storage_charge: existing.storage_charge,
+                    expected_nft: existing.expected_nft,
+                }
+            }
+            None => {
+                self.update_funder_stats(drop.funder_id.as_ref(), |stats| stats.created += 1);
+                self.add_funder_drop(drop.funder_id.as_ref(), pk);
+                self.lock_allowance(drop.allowance);
+                DropInfo {
+                    created_at: env::block_timestamp(),
+                    ..drop
+                }
+            }
+        };
+        self.accounts.insert(pk, &drop);
+        self.lock(amount);
+    }
+
+    /// Removes the drops of `public_keys` funded by the caller, deletes their access keys and refunds their
+    /// balances in one transfer. Returns the number of cancelled drops and the skipped keys.
+    fn internal_cancel_drops(&mut self, public_keys: Vec<PublicKey>) -> (u32, Vec<PublicKey>) {
+        self.assert_not_paused();
+        assert!(public_keys.len() <= MAX_CANCEL_KEYS, "Too many keys to cancel at once");
+        let funder_id = env::predecessor_account_id();
+        let mut refund: Balance = 0;
+        let mut cancelled = Vec::new();
+        let mut skipped = Vec::new();
+        for pk in public_keys {
+            match self.accounts.get(&pk) {
+                Some(drop) if drop.funder_id.as_ref() == Some(&funder_id) && drop.nft.is_none() => {}
+                _ => {
+                    skipped.push(pk);
+                    continue;
+                }
+            }
+            let drop = self.internal_remove_drop(&pk).unwrap();
+            refund += drop.balance;
+            cancelled.push(pk);
+        }
+        if cancelled.is_empty() {
+            return (0, skipped);
+        }
+
+        let count = cancelled.len() as u32;
+        self.reclaimed_count += u64::from(count);
+        self.update_funder_stats(Some(&funder_id), |stats| stats.reclaimed += u64::from(count));
+        let mut promise = Promise::new(env::current_account_id());
+        for pk in cancelled {
+            promise = promise.delete_key(pk);
+        }
+        Promise::new(funder_id).transfer(refund);
+        (count, skipped)
+    }
+
+    /// Removes the drop of the key this tx is signed with, checking the password if the drop requires one.
+    fn internal_take_drop(&mut self, password: Option<String>) -> DropInfo {
+        self.assert_not_claiming();
+        let drop = self
+            .internal_remove_drop(&env::signer_account_pk())
+            .expect("Unexpected public key");
+        assert!(drop.random.is_none(), "Random drops can only be claimed with claim");
+        self.assert_claimable(&drop, password);
+        drop
+    }
+
+    /// Same as `internal_take_drop`, for claims finished by `on_account_created_and_claimed`. The key stays
+    /// marked as in flight until then, so a second submission fails with a clear message.
+    fn internal_take_drop_in_flight(&mut self, password: Option<String>) -> DropInfo {
+        let drop = self.internal_take_drop(password);
+        self.claiming.insert(&env::signer_account_pk());
+        drop
+    }
+
+    /// Method names to record on a new drop whose key gets `default_claim_methods`. `None` while those are
+    /// the built-in ACCESS_KEY_METHOD_NAMES, which drops fall back to.
+    fn drop_method_names(&self) -> Option<String> {
+        Some(self.default_claim_methods.clone()).filter(|methods| methods != ACCESS_KEY_METHOD_NAMES)
+    }
+
+    /// Panics if a claim with the signing key is waiting for its callback.
+    fn assert_not_claiming(&self) {
+        assert!(!self.claiming.contains(&env::signer_account_pk()), "Claim already in progress");
+    }
+
+    /// Panics if the last partial claim of a multi-use drop was less than `claim_cooldown_ns` ago.
+    fn assert_cooldown_passed(&self, drop: &DropInfo) {
+        assert!(
+            env::block_timestamp() >= drop.last_claim_at.saturating_add(self.claim_cooldown_ns),
+            "Claim cooldown active"
+        );
+    }
+
+    /// Pays one claim of a random drop. The last use deletes the key and refunds the rest of the pool
+    /// to the funder. Claims must be at least `claim_cooldown_ns` apart, as with `claim_amount`.
+    fn internal_claim_random(
+        &mut self,
+        account_id: AccountId,
+        mut drop: DropInfo,
+        password: Option<String>,
+        memo: Option<String>,
+    ) -> Promise {
+        let public_key = env::signer_account_pk();
+        self.assert_claimable(&drop, password);
+        self.assert_cooldown_passed(&drop);
+        let random = drop.random.as_mut().expect("Not a random drop");
+        // The last claims get whatever is left if the pool cannot cover the picked amount
+        let amount = random.pick().min(drop.balance);
+        random.uses_left -= 1;
+        if random.uses_left == 0 || amount == drop.balance {
+            let drop = self.internal_remove_drop(&public_key).expect("Unexpected public key");
+            Promise::new(env::current_account_id()).delete_key(public_key);
+            refund_funder(drop.funder_id.clone(), drop.balance - amount);
+            self.update_funder_stats(drop.funder_id.as_ref(), |stats| stats.claimed += 1);
+        } else {
+            drop.balance -= amount;
+            drop.last_claim_at = env::block_timestamp();
+            self.accounts.insert(&public_key, &drop);
+            self.unlock(amount);
+        }
+        self.record_claim(amount);
+        emit(EventKind::Claim(vec![ClaimLog {
+            account_id: account_id.clone(),
+            amount: amount.into(),
+            memo,
+            message: drop.claim_message.clone(),
+        }]));
+        Promise::new(account_id).transfer(amount)
+    }
+
+    /// Removes the drop registered under `pk` and releases its balance and allowance from the totals.
+    fn internal_remove_drop(&mut self, pk: &PublicKey) -> Option<DropInfo> {
+        let drop = self.accounts.remove(pk)?;
+        self.unlock(drop.balance);
+        self.locked_allowances = self.locked_allowances.saturating_sub(drop.allowance);
+        self.remove_funder_drop(drop.funder_id.as_ref(), pk);
+        if let Some((account_id, charged)) = drop.storage_charge.as_ref() {
+            self.release_storage_balance(account_id, *charged);
+        }
+        Some(drop)
+    }
+
+    /// Puts back a drop removed by a claim or withdrawal that failed.
+    fn internal_restore_drop(&mut self, pk: &PublicKey, mut drop: DropInfo) {
+        self.lock(drop.balance);
+        self.lock_allowance(drop.allowance);
+        self.add_funder_drop(drop.funder_id.as_ref(), pk);
+        // The storage charge was given back on removal: take it again, or leave the storage to the contract
+        // if the balance was withdrawn in the meantime.
+        if let Some((account_id, charged)) = drop.storage_charge.take() {
+            if self.use_storage_balance(&account_id, charged) {
+                drop.storage_charge = Some((account_id, charged));
+            }
+        }
+        self.accounts.insert(pk, &drop);
+    }
+
+    /// Lists `pk` among the drops of the funder, if the drop has a known funder.
+    fn add_funder_drop(&mut self, funder_id: Option<&AccountId>, pk: &PublicKey) {
+        if let Some(funder_id) = funder_id {
+            let mut drops = self.drops_by_funder.get(funder_id).unwrap_or_else(|| {
+                let prefix = [
+                    StorageKey::FunderDrops.with_prefix(&self.storage_prefix),
+                    env::sha256(funder_id.as_bytes()),
+                ].concat();
+                UnorderedSet::new(prefix)
+            });
+            drops.insert(pk);
+            self.drops_by_funder.insert(funder_id, &drops);
+        }
+    }
+
+    /// Removes `pk` from the drops of the funder, dropping the funder's list once it is empty.
+    fn remove_funder_drop(&mut self, funder_id: Option<&AccountId>, pk: &PublicKey) {
+        let funder_id = match funder_id {
+            Some(funder_id) => funder_id,
+            None => return,
+        };
+        if let Some(mut drops) = self.drops_by_funder.get(funder_id) {
+            drops.remove(pk);
+            if drops.is_empty() {
+                self.drops_by_funder.remove(funder_id);
+            } else {
+                self.drops_by_funder.insert(funder_id, &drops);
+            }
+        }
+    }
+
+    /// Panics if the drop is locked by a pending commitment or the password does not match.
+    fn assert_claimable(&self, drop: &DropInfo, password: Option<String>) {
+        if let Some(commitment) = drop.commitment.as_ref() {
+            assert!(
+                env::block_height() >= commitment.locked_until,
+                "Drop is locked by a pending commitment"
+            );
+        }
+        if let Some(password_hash) = drop.password_hash {
+            let password = password.expect("Password is required for this drop");
+            assert_eq!(
+                env::sha256_array(password.as_bytes()),
+                password_hash,
+                "Incorrect password"
+            );
+        }
+    }
+
+    /// Adds to the total locked balance. Overflow indicates a bug, so it panics.
+    fn lock(&mut self, amount: Balance) {
+        self.total_locked = self
+            .total_locked
+            .checked_add(amount)
+            .expect("Total locked balance overflow");
+    }
+
+    /// Adds to the total of access key allowances. Overflow indicates a bug, so it panics.
+    fn lock_allowance(&mut self, allowance: Balance) {
+        self.locked_allowances = self
+            .locked_allowances
+            .checked_add(allowance)
+            .expect("Total locked allowance overflow");
+    }
+
+    /// Counts a claim of the drop under `public_key` by `account_id`, panicking if the account already
+    /// received `max_claims_per_account` claims of it. Nothing is tracked while there is no limit.
+    fn record_account_claim(&mut self, public_key: &PublicKey, account_id: &AccountId) {
+        if self.max_claims_per_account == 0 {
+            return;
+        }
+        let key = (public_key.clone(), account_id.clone());
+        let claims = self.account_claims.get(&key).unwrap_or(0);
+        assert!(claims < self.max_claims_per_account, "Per-account claim limit reached");
+        self.account_claims.insert(&key, &(claims + 1));
+    }
+
+    /// Counts a successful claim in the lifetime totals.
+    fn record_claim(&mut self, amount: Balance) {
+        self.claimed_count += 1;
+        self.claimed_amount = self.claimed_amount.saturating_add(amount);
+    }
+
+    /// Applies `update` to the counters of the funder, if the drop has a known funder.
+    fn update_funder_stats(&mut self, funder_id: Option<&AccountId>, update: impl FnOnce(&mut FunderStats)) {
+        if let Some(funder_id) = funder_id {
+            let mut stats = self.funder_stats.get(funder_id).unwrap_or_default();
+            update(&mut stats);
+            self.funder_stats.insert(funder_id, &stats);
         }
     }
+
+    /// Removes from the total locked balance, saturating at zero.
+    fn unlock(&mut self, amount: Balance) {
+        self.total_locked = self.total_locked.saturating_sub(amount);
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(test)]
 mod tests {
-    use near_sdk::test_utils::{VMContextBuilder};
+    use near_sdk::mock::VmAction;
+    use near_sdk::serde_json;
+    use near_sdk::test_utils::{get_created_receipts, get_logs, VMContextBuilder};
     use near_sdk::{testing_env};
 
     use super::*;
@@ -265,334 +2080,5314 @@ mod tests {
         "linkdrop".parse().unwrap()
     }
 
-    fn bob() -> AccountId {
-        "bob".parse().unwrap()
+    fn bob() -> AccountId {
+        "bob".parse().unwrap()
+    }
+
+    fn sub_account() -> AccountId {
+        "bob.linkdrop".parse().unwrap()
+    }
+
+    /// Creates a new instance of the linkdrop contract in which bob sends a drop for the default test key
+    /// with `deposit` attached, using `send` (e.g. `LinkDrop::send`). Returns the contract and the key.
+    fn send_from_bob<R>(deposit: Balance, send: impl FnOnce(&mut LinkDrop, PublicKey) -> R) -> (LinkDrop, PublicKey) {
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        send(&mut contract, pk.clone());
+        (contract, pk)
+    }
+
+    /// Sets up the context of a claim signed with the drop key `pk` while the contract holds `balance`,
+    /// in a block with a fixed random seed.
+    fn set_claim_context(pk: &PublicKey, balance: Balance) {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk.clone())
+            .account_balance(balance)
+            .random_seed([7; 32])
+            .context.clone()
+        );
+    }
+
+    #[test]
+    fn test_create_account() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to an extremely small amount
+        let deposit = 1_000_000;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create bob's account with the PK
+        contract.create_account(sub_account(), pk);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_create_invalid_account() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to an extremely small amount
+        let deposit = 1_000_000;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Attempt to create an invalid account with the PK
+        contract.create_account("XYZ".parse().unwrap(), pk);
+    }
+
+    #[test]
+    fn test_get_missing_balance_fails() {
+        // Create a new instance of the linkdrop contract
+        let contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .context.clone()
+        );
+
+        assert_eq!(contract.get_key_balance(pk), Err(LinkdropError::KeyMissing));
+    }
+
+    #[test]
+    fn test_get_missing_balance_success() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // try getting the balance of the key
+        let balance:u128 = contract.get_key_balance(pk).unwrap().0;
+        assert_eq!(
+            balance,
+            deposit - ACCESS_KEY_ALLOWANCE
+        );
+    }
+
+    #[test]
+    fn test_get_key_information_batch() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let missing_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Look up a mix of existing and missing keys
+        let infos = contract.get_key_information_batch(vec![missing_pk.clone(), pk.clone(), missing_pk]);
+        assert_eq!(infos.len(), 3);
+        assert!(infos[0].is_none());
+        assert_eq!(infos[1].as_ref().unwrap().balance, U128(deposit - ACCESS_KEY_ALLOWANCE));
+        assert!(infos[2].is_none());
+    }
+
+    #[test]
+    fn test_is_claimable() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let missing_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 100)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Only the registered key is claimable
+        assert!(contract.is_claimable(pk));
+        assert!(!contract.is_claimable(missing_pk));
+    }
+
+    #[test]
+    fn test_is_claimable_when_paused() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 100)
+            .context.clone()
+        );
+
+        // Create the linkdrop and pause the contract
+        contract.send(pk.clone());
+        contract.set_paused(true);
+
+        // The drop exists but cannot be claimed while paused
+        assert!(!contract.is_claimable(pk));
+    }
+
+    #[test]
+    fn test_claim_invalid_account() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk);
+
+        // Create the second public key, which has no drop
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+
+        // Now, send new transaction to linkdrop contract signed with the unregistered key
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk2.clone())
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Attempt to create the account and claim
+        let result = contract.create_account_and_claim(sub_account(), pk2, None, None);
+        assert_eq!(result.err(), Some(LinkdropError::KeyNotRegistered));
+    }
+
+    #[test]
+    fn test_create_named_claim() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let new_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Claim into a subaccount named after the prefix
+        contract.create_named_claim("claim-3f2a".to_string(), new_pk);
+
+        // The account is created under the linkdrop contract
+        let expected: AccountId = "claim-3f2a.linkdrop".parse().unwrap();
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| receipt.receiver_id == expected));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid account prefix")]
+    fn test_create_named_claim_invalid_prefix() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let new_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+
+        // Uppercase letters and dots are rejected
+        contract.create_named_claim("Claim.sub".to_string(), new_pk);
+    }
+
+    #[test]
+    fn test_drop_claim() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk.into())
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Create the second public key
+        let pk2 = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Attempt to create the account and claim
+        contract.create_account_and_claim(sub_account(), pk2, None, None).unwrap();
+    }
+
+    /// Claims a fresh drop with `create_account_and_claim` and resolves its callback with `promise_result`.
+    /// Returns the contract, the drop key and the drop balance.
+    fn resolve_create_account_and_claim(
+        promise_result: PromiseResult,
+        fallback_account: Option<AccountId>,
+    ) -> (LinkDrop, PublicKey, Balance) {
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Create the linkdrop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        match fallback_account {
+            Some(fallback_account) => contract.send_with_fallback(pk.clone(), fallback_account),
+            None => contract.send(pk.clone()),
+        };
+        let drop = contract.accounts.get(&pk).unwrap();
+
+        // Start the claim, which takes the drop out until the callback
+        let context = VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk.clone())
+            .account_balance(deposit)
+            .context.clone();
+        testing_env!(context.clone());
+        let new_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        contract.create_account_and_claim(sub_account(), new_pk, None, None).unwrap();
+        assert!(contract.accounts.get(&pk).is_none());
+
+        // Resolve the callback with the given outcome of the account creation
+        testing_env!(
+            context,
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![promise_result]
+        );
+        contract.on_account_created_and_claimed(sub_account(), drop, 0);
+        (contract, pk, deposit - ACCESS_KEY_ALLOWANCE)
+    }
+
+    /// Refund events logged so far, parsed from their `EVENT_JSON:` logs.
+    fn refund_events() -> Vec<serde_json::Value> {
+        get_logs()
+            .iter()
+            .filter_map(|log| log.strip_prefix("EVENT_JSON:"))
+            .map(|log| serde_json::from_str::<serde_json::Value>(log).unwrap())
+            .filter(|event| event["event"] == "refund")
+            .collect()
+    }
+
+    /// Resolves `on_account_created` for a plain account creation, already retried `retries` times, with
+    /// the given outcome. The context stays set up with the owner as predecessor.
+    fn resolve_create_account(contract: &mut LinkDrop, promise_result: PromiseResult, retries: u32) {
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .account_balance(ACCESS_KEY_ALLOWANCE)
+            .context.clone(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![promise_result]
+        );
+        contract.on_account_created(sub_account(), bob(), U128(ACCESS_KEY_ALLOWANCE), Some(pk), retries, None);
+    }
+
+    /// Returns the JSON arguments of the `on_account_created` callback scheduled by the last call and sets up
+    /// the context to resolve it with the given outcome.
+    fn take_create_account_callback(promise_result: PromiseResult) -> serde_json::Value {
+        let receipts = get_created_receipts();
+        let callback = receipts.iter().find(|receipt| receipt.receiver_id == linkdrop()).unwrap();
+        let args = match &callback.actions[0] {
+            VmAction::FunctionCall { function_name, args, .. } if function_name == "on_account_created" => {
+                serde_json::from_slice(args).unwrap()
+            }
+            action => panic!("Unexpected action {:?}", action),
+        };
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![promise_result]
+        );
+        args
+    }
+
+    #[test]
+    fn test_create_account_callback_failure_logs_refund() {
+        resolve_create_account(&mut LinkDrop::new(linkdrop(), None), PromiseResult::Failed, 0);
+
+        // The attached deposit goes back to the caller and the refund is logged
+        assert_eq!(refund_events(), vec![serde_json::json!({
+            "standard": "linkdrop",
+            "version": "1.0.0",
+            "event": "refund",
+            "data": [{
+                "account_id": sub_account(),
+                "amount": U128(ACCESS_KEY_ALLOWANCE),
+                "reason": "creation_failed",
+            }]
+        })]);
+    }
+
+    #[test]
+    fn test_create_account_callback_success_logs_no_refund() {
+        resolve_create_account(&mut LinkDrop::new(linkdrop(), None), PromiseResult::Successful(vec![]), 0);
+
+        assert!(refund_events().is_empty());
+    }
+
+    #[test]
+    fn test_create_account_failure_queued_for_retry() {
+        // Create a new instance of the linkdrop contract that retries failed creations once
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.set_max_creation_retries(1);
+
+        // The first attempt fails
+        resolve_create_account(&mut contract, PromiseResult::Failed, 0);
+
+        // The deposit is kept for another attempt instead of being refunded
+        assert!(refund_events().is_empty());
+        assert!(get_created_receipts().is_empty());
+        assert_eq!(contract.retry_locked, ACCESS_KEY_ALLOWANCE);
+        assert_eq!(contract.get_retry_queue(0, 10).len(), 1);
+
+        // The owner retries it, which builds the same account creation again
+        assert_eq!(contract.retry_failed_creations(10), 1);
+        assert!(contract.get_retry_queue(0, 10).is_empty());
+        assert_eq!(contract.retry_locked, 0);
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == sub_account()).unwrap();
+        assert_eq!(receipt.actions, vec![
+            VmAction::CreateAccount,
+            VmAction::AddKeyWithFullAccess { public_key: pk, nonce: 0 },
+            VmAction::Transfer { deposit: ACCESS_KEY_ALLOWANCE },
+        ]);
+    }
+
+    #[test]
+    fn test_refund_failed_creation() {
+        // Create a new instance of the linkdrop contract that retries failed creations once
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.set_max_creation_retries(1);
+
+        // The first attempt of bob's creation fails and is queued
+        resolve_create_account(&mut contract, PromiseResult::Failed, 0);
+        assert_eq!(contract.get_retry_queue(0, 10).len(), 1);
+
+        // Bob takes his deposit back instead of waiting for a retry
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .account_balance(ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+        contract.refund_failed_creation(0);
+
+        assert!(contract.get_retry_queue(0, 10).is_empty());
+        assert_eq!(contract.retry_locked, 0);
+        assert_eq!(refund_events().len(), 1);
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, bob());
+        assert_eq!(receipts[0].actions, vec![VmAction::Transfer { deposit: ACCESS_KEY_ALLOWANCE }]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the caller of the failed creation can refund it")]
+    fn test_refund_failed_creation_of_another_account() {
+        // Create a new instance of the linkdrop contract that retries failed creations once
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.set_max_creation_retries(1);
+
+        // The first attempt of bob's creation fails and is queued
+        resolve_create_account(&mut contract, PromiseResult::Failed, 0);
+
+        // Someone else attempts to take the deposit
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(sub_account())
+            .context.clone()
+        );
+        contract.refund_failed_creation(0);
+    }
+
+    #[test]
+    fn test_create_account_failure_refunded_after_max_retries() {
+        // Create a new instance of the linkdrop contract that retries failed creations once
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.set_max_creation_retries(1);
+
+        // The retry fails too
+        resolve_create_account(&mut contract, PromiseResult::Failed, 1);
+
+        // This time the deposit goes back to the caller
+        assert!(contract.get_retry_queue(0, 10).is_empty());
+        assert_eq!(refund_events().len(), 1);
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == bob()).unwrap();
+        assert_eq!(receipt.actions, vec![VmAction::Transfer { deposit: ACCESS_KEY_ALLOWANCE }]);
+    }
+
+    #[test]
+    fn test_create_account_and_claim_callback_success() {
+        let (contract, pk, balance) = resolve_create_account_and_claim(PromiseResult::Successful(vec![]), None);
+
+        // The drop is gone and its key is deleted exactly once
+        assert!(contract.accounts.get(&pk).is_none());
+        assert_eq!(contract.get_total_locked().0, 0);
+        assert_eq!(contract.get_monitoring_snapshot().claimed_amount.0, balance);
+        let deletions: Vec<_> = get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter(|action| matches!(action, VmAction::DeleteKey { .. }))
+            .collect();
+        assert_eq!(deletions, vec![VmAction::DeleteKey { public_key: pk }]);
+        assert!(refund_events().is_empty());
+    }
+
+    #[test]
+    fn test_create_account_and_claim_callback_failure() {
+        let (contract, pk, balance) = resolve_create_account_and_claim(PromiseResult::Failed, None);
+
+        // The drop is back with its full balance, its key is kept and can be claimed again
+        assert!(!contract.claiming.contains(&pk));
+        assert_eq!(contract.get_key_balance(pk.clone()).unwrap().0, balance);
+        assert_eq!(contract.get_total_locked().0, balance);
+        assert_eq!(contract.get_drops_by_funder(bob(), 0, 10), vec![(pk, U128(balance))]);
+        assert_eq!(contract.get_monitoring_snapshot().claimed_count, 0);
+        assert!(get_created_receipts()
+            .iter()
+            .flat_map(|receipt| receipt.actions.iter())
+            .all(|action| !matches!(action, VmAction::DeleteKey { .. })));
+
+        // The drop going back is logged as a refund
+        let events = refund_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["data"], serde_json::json!([{
+            "account_id": sub_account(),
+            "amount": U128(balance),
+            "reason": "creation_failed",
+        }]));
+    }
+
+    #[test]
+    fn test_create_account_and_claim_callback_failure_pays_fallback() {
+        let fallback: AccountId = "alice".parse().unwrap();
+        let (contract, pk, balance) =
+            resolve_create_account_and_claim(PromiseResult::Failed, Some(fallback.clone()));
+
+        // The drop is not restored, its balance goes to the fallback account instead
+        assert!(contract.accounts.get(&pk).is_none());
+        assert_eq!(contract.get_total_locked().0, 0);
+        assert_eq!(contract.get_monitoring_snapshot().claimed_amount.0, balance);
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == fallback).unwrap();
+        assert_eq!(receipt.actions, vec![VmAction::Transfer { deposit: balance }]);
+
+        // The key has no drop left to claim, so it is deleted
+        assert!(receipts
+            .iter()
+            .flat_map(|receipt| receipt.actions.iter())
+            .any(|action| *action == VmAction::DeleteKey { public_key: pk.clone() }));
+        assert!(refund_events().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the funder of the drop can change its settings")]
+    fn test_send_fallback_to_drop_of_another_funder() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Bob creates a plain linkdrop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+
+        // Someone who knows the key tries to redirect failed claims to their own account
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(sub_account())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send_with_fallback(pk, sub_account());
+    }
+
+    #[test]
+    fn test_drop_claim_adds_new_account_keys() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let app_pk: PublicKey = "4BTYRHcT1s5WCBFdY3gn5rxsdB5tAQkTrUVLhrhDU9Vb"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop with a key for the app to add to the new account
+        contract.send_with_new_account_keys(pk.clone(), vec![LimitedAccessKey {
+            public_key: app_pk.clone(),
+            allowance: U128(100),
+            receiver_id: bob(),
+            method_names: "play".to_string(),
+        }]);
+
+        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Create the account and claim
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        contract.create_account_and_claim(sub_account(), pk2, None, None).unwrap();
+
+        // The new account gets the app key next to its full access key
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == sub_account()).unwrap();
+        assert!(receipt.actions.contains(&VmAction::AddKeyWithFunctionCall {
+            public_key: app_pk,
+            nonce: 0,
+            allowance: Some(100),
+            receiver_id: bob(),
+            function_names: vec!["play".to_string()],
+        }));
+    }
+
+    #[test]
+    fn test_top_up_by_another_account_keeps_new_account_keys() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let app_pk: PublicKey = "4BTYRHcT1s5WCBFdY3gn5rxsdB5tAQkTrUVLhrhDU9Vb"
+            .parse()
+            .unwrap();
+
+        // Bob creates the linkdrop with a key for the app to add to the new account
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send_with_new_account_keys(pk.clone(), vec![LimitedAccessKey {
+            public_key: app_pk.clone(),
+            allowance: U128(100),
+            receiver_id: bob(),
+            method_names: "play".to_string(),
+        }]);
+
+        // Someone else adds to the drop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(sub_account())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+
+        // The key added to the new account is still bob's
+        let drop = contract.accounts.get(&pk).unwrap();
+        let keys: Vec<PublicKey> = drop.new_account_limited_keys.unwrap().into_iter().map(|key| key.public_key).collect();
+        assert_eq!(keys, vec![app_pk]);
+        assert_eq!(drop.funder_id, Some(bob()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the funder of the drop can change its settings")]
+    fn test_send_new_account_keys_to_drop_of_another_funder() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Bob creates a plain linkdrop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+
+        // Someone who knows the key tries to add their own key to the account created by the claim
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(sub_account())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send_with_new_account_keys(pk, vec![LimitedAccessKey {
+            public_key: "4BTYRHcT1s5WCBFdY3gn5rxsdB5tAQkTrUVLhrhDU9Vb".parse().unwrap(),
+            allowance: U128(100),
+            receiver_id: sub_account(),
+            method_names: "transfer".to_string(),
+        }]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract bytes cannot be empty")]
+    fn test_drop_claim_with_empty_contract_bytes() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Create the second public key
+        let pk2 = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Attempt to create the account with an empty contract
+        contract.create_account_and_claim(sub_account(), pk2, Some(vec![].into()), None).unwrap();
+    }
+
+    #[test]
+    fn test_claim_protected_drop_with_correct_password() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the password protected linkdrop
+        contract.send_protected(pk.clone(), env::sha256_array(b"secret").into());
+
+        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk.clone())
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Claim with the right password
+        contract.claim(bob(), Some("secret".to_string()), None);
+        assert!(contract.accounts.get(&pk).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Incorrect password")]
+    fn test_claim_protected_drop_with_wrong_password() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the password protected linkdrop
+        contract.send_protected(pk.clone(), env::sha256_array(b"secret").into());
+
+        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Attempt to claim with the wrong password
+        contract.claim(bob(), Some("guess".to_string()), None);
+    }
+
+    #[test]
+    fn test_claim_unprotected_drop_ignores_password() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create a plain linkdrop
+        contract.send(pk.clone());
+
+        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk.clone())
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Any password is ignored for a drop without one
+        contract.claim(bob(), Some("anything".to_string()), None);
+        assert!(contract.accounts.get(&pk).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the funder of the drop can change its settings")]
+    fn test_send_protected_to_drop_of_another_funder() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Bob creates a plain linkdrop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+
+        // Someone who knows the key tries to lock it behind their own password
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(sub_account())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send_protected(pk, env::sha256_array(b"secret").into());
+    }
+
+    #[test]
+    fn test_send_with_metadata() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop with a campaign ID attached
+        contract.send_with_metadata(pk.clone(), Some("campaign-42".to_string()));
+
+        // Read it back through the view
+        let info = contract.get_key_information(pk).unwrap();
+        assert_eq!(info.metadata, Some("campaign-42".to_string()));
+        assert_eq!(info.balance.0, deposit - ACCESS_KEY_ALLOWANCE);
+    }
+
+    #[test]
+    fn test_get_key_information_all_fields() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Timestamp of the block the drop is created in
+        let timestamp = 1_650_000_000_000_000_000;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .block_timestamp(timestamp)
+            .context.clone()
+        );
+
+        // Bob creates a random drop with three uses and attaches a campaign ID to it
+        contract.send_random(pk.clone(), U128(1), U128(2), 3);
+        contract.send_with_metadata(pk.clone(), Some("campaign-42".to_string()));
+
+        // Every field is read back from the stored drop
+        let info = contract.get_key_information(pk).unwrap();
+        assert_eq!(serde_json::to_value(&info).unwrap(), serde_json::json!({
+            "balance": U128(ACCESS_KEY_ALLOWANCE * 2),
+            "metadata": "campaign-42",
+            "created_at": timestamp,
+            "estimated_claim_gas": CREATE_ACCOUNT_AND_CLAIM_GAS + ON_CREATE_ACCOUNT_CALLBACK_GAS,
+            "funder_id": bob(),
+            "expires_at": null,
+            "uses_remaining": 3,
+        }));
+    }
+
+    #[test]
+    fn test_send_records_created_at() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Timestamp of the block the drop is created in
+        let timestamp = 1_650_000_000_000_000_000;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .block_timestamp(timestamp)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Topping it up in a later block keeps the creation time
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .block_timestamp(timestamp + 1)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+
+        let info = contract.get_key_information(pk).unwrap();
+        assert_eq!(info.created_at, timestamp);
+    }
+
+    #[test]
+    #[should_panic(expected = "Metadata exceeds MAX_METADATA_LENGTH")]
+    fn test_send_with_metadata_too_long() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 100)
+            .context.clone()
+        );
+
+        // Attempt to attach metadata over the cap
+        contract.send_with_metadata(pk, Some("a".repeat(MAX_METADATA_LENGTH + 1)));
+    }
+
+    #[test]
+    fn test_claim_does_not_refund_allowance_to_funder() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain with bob as the funder
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Claim the drop into an existing account
+        contract.claim(sub_account(), None, None);
+
+        // The recipient gets the drop balance, the allowance the key may have burnt stays with the contract
+        let receipts = get_created_receipts();
+        let transfer_to = |account_id: AccountId| {
+            receipts
+                .iter()
+                .find(|receipt| receipt.receiver_id == account_id)
+                .map(|receipt| receipt.actions.clone())
+        };
+        assert_eq!(
+            transfer_to(sub_account()),
+            Some(vec![VmAction::Transfer { deposit: deposit - ACCESS_KEY_ALLOWANCE }])
+        );
+        assert_eq!(transfer_to(bob()), None);
+    }
+
+    #[test]
+    fn test_claim_with_receipt_returns_amount() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // The claimed amount is returned and transferred
+        let amount = contract.claim_with_receipt(sub_account(), None, None);
+        assert_eq!(amount.0, deposit - ACCESS_KEY_ALLOWANCE);
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == sub_account()).unwrap();
+        assert_eq!(receipt.actions, vec![VmAction::Transfer { deposit: amount.0 }]);
+    }
+
+    #[test]
+    fn test_claim_with_memo_logs_event() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Claim the drop with a memo
+        contract.claim(sub_account(), None, Some("invoice 17".to_string()));
+
+        // The memo is logged in a claim event
+        let logs = get_logs();
+        assert_eq!(logs.len(), 1);
+        let event: serde_json::Value = serde_json::from_str(logs[0].strip_prefix("EVENT_JSON:").unwrap()).unwrap();
+        assert_eq!(event, serde_json::json!({
+            "standard": "linkdrop",
+            "version": "1.0.0",
+            "event": "claim",
+            "data": [{
+                "account_id": sub_account(),
+                "amount": U128(deposit - ACCESS_KEY_ALLOWANCE),
+                "memo": "invoice 17",
+            }]
+        }));
+    }
+
+    #[test]
+    fn test_claim_logs_funder_message() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop with a message
+        contract.send_with_message(pk.clone(), "Happy Birthday from Alice".to_string());
+
+        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Claim the drop
+        contract.claim(sub_account(), None, None);
+
+        // The message is logged in the claim event
+        let logs = get_logs();
+        assert_eq!(logs.len(), 1);
+        let event: serde_json::Value = serde_json::from_str(logs[0].strip_prefix("EVENT_JSON:").unwrap()).unwrap();
+        assert_eq!(event["data"][0]["message"], "Happy Birthday from Alice");
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the funder of the drop can change its settings")]
+    fn test_send_message_to_drop_of_another_funder() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Bob creates the linkdrop with a message
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send_with_message(pk.clone(), "Happy Birthday from Bob".to_string());
+
+        // Someone who knows the key tries to replace the message
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(sub_account())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send_with_message(pk, "Happy Birthday from someone else".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Memo exceeds MAX_MEMO_LENGTH")]
+    fn test_claim_with_memo_too_long() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Claim with a memo longer than the cap
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .context.clone()
+        );
+        contract.claim(sub_account(), None, Some("m".repeat(MAX_MEMO_LENGTH + 1)));
+    }
+
+    #[test]
+    fn test_claim_amount_leaves_remainder() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        let balance = deposit - ACCESS_KEY_ALLOWANCE;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Withdraw a third of the balance
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk.clone())
+            .account_balance(deposit)
+            .context.clone()
+        );
+        contract.claim_amount(sub_account(), U128(balance / 3), None);
+
+        // The rest stays on the drop and the key is kept
+        assert_eq!(contract.get_key_balance(pk).unwrap().0, balance - balance / 3);
+        assert_eq!(contract.get_total_locked().0, balance - balance / 3);
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, sub_account());
+        assert_eq!(receipts[0].actions, vec![VmAction::Transfer { deposit: balance / 3 }]);
+    }
+
+    /// Creates a drop whose recipients can claim it at most twice, and claims it twice into `sub_account()`.
+    fn claim_amount_up_to_account_limit() -> (LinkDrop, PublicKey) {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // The owner limits each account to two claims per drop and creates the linkdrop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.set_max_claims_per_account(2);
+        contract.send(pk.clone());
+
+        // The same account withdraws twice
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk.clone())
+            .account_balance(deposit)
+            .context.clone()
+        );
+        contract.claim_amount(sub_account(), U128(ACCESS_KEY_ALLOWANCE), None);
+        contract.claim_amount(sub_account(), U128(ACCESS_KEY_ALLOWANCE), None);
+        (contract, pk)
+    }
+
+    #[test]
+    fn test_claim_amount_up_to_account_limit() {
+        let (mut contract, pk) = claim_amount_up_to_account_limit();
+        let balance = ACCESS_KEY_ALLOWANCE * 99;
+
+        // Both withdrawals went through and another account can still claim
+        assert_eq!(contract.get_key_balance(pk.clone()).unwrap().0, balance - 2 * ACCESS_KEY_ALLOWANCE);
+        contract.claim_amount(bob(), U128(ACCESS_KEY_ALLOWANCE), None);
+        assert_eq!(contract.get_key_balance(pk).unwrap().0, balance - 3 * ACCESS_KEY_ALLOWANCE);
+    }
+
+    #[test]
+    #[should_panic(expected = "Per-account claim limit reached")]
+    fn test_claim_amount_over_account_limit() {
+        let (mut contract, _) = claim_amount_up_to_account_limit();
+
+        // A third withdrawal into the same account is rejected
+        contract.claim_amount(sub_account(), U128(ACCESS_KEY_ALLOWANCE), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Per-account claim limit reached")]
+    fn test_claim_over_account_limit() {
+        let (mut contract, _) = claim_amount_up_to_account_limit();
+
+        // Claiming the rest of the drop into the same account counts as a claim too
+        contract.claim(sub_account(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Claim cooldown active")]
+    fn test_claim_amount_within_cooldown() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        // One minute between withdrawals
+        let cooldown = 60_000_000_000;
+
+        // The owner sets the cooldown and creates the linkdrop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.set_claim_cooldown(U64(cooldown));
+        contract.send(pk.clone());
+
+        // Withdraw once
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk.clone())
+            .account_balance(deposit)
+            .block_timestamp(cooldown)
+            .context.clone()
+        );
+        contract.claim_amount(sub_account(), U128(ACCESS_KEY_ALLOWANCE), None);
+
+        // Withdrawing again before the cooldown has passed is rejected
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .block_timestamp(cooldown * 2 - 1)
+            .context.clone()
+        );
+        contract.claim_amount(sub_account(), U128(ACCESS_KEY_ALLOWANCE), None);
+    }
+
+    #[test]
+    fn test_claim_amount_after_cooldown() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        // One minute between withdrawals
+        let cooldown = 60_000_000_000;
+
+        // The owner sets the cooldown and creates the linkdrop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.set_claim_cooldown(U64(cooldown));
+        contract.send(pk.clone());
+
+        // Withdraw twice, a full cooldown apart
+        for timestamp in [cooldown, cooldown * 2] {
+            testing_env!(
+                VMContextBuilder::new()
+                .current_account_id(linkdrop())
+                .predecessor_account_id(linkdrop())
+                .signer_account_pk(pk.clone())
+                .account_balance(deposit)
+                .block_timestamp(timestamp)
+                .context.clone()
+            );
+            contract.claim_amount(sub_account(), U128(ACCESS_KEY_ALLOWANCE), None);
+        }
+
+        // Both withdrawals went through
+        assert_eq!(contract.get_key_balance(pk).unwrap().0, deposit - ACCESS_KEY_ALLOWANCE * 3);
+    }
+
+    #[test]
+    fn test_claim_amount_of_whole_balance_deletes_key() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Withdraw the exact balance
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk.clone())
+            .account_balance(deposit)
+            .context.clone()
+        );
+        contract.claim_amount(sub_account(), U128(deposit - ACCESS_KEY_ALLOWANCE), None);
+
+        // The drop is gone and its key is deleted
+        assert!(contract.get_key_information(pk.clone()).is_err());
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == linkdrop()).unwrap();
+        assert_eq!(receipt.actions, vec![VmAction::DeleteKey { public_key: pk }]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount exceeds the drop balance")]
+    fn test_claim_amount_over_balance() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Try to withdraw more than the drop holds
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .context.clone()
+        );
+        contract.claim_amount(sub_account(), U128(ACCESS_KEY_ALLOWANCE + 1), None);
+    }
+
+    #[test]
+    fn test_commit_and_reveal_claim() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Commit to claiming into bob's sub account
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk.clone())
+            .account_balance(deposit)
+            .block_index(10)
+            .context.clone()
+        );
+        let commitment = env::sha256_array(&[sub_account().as_bytes(), b"salt"].concat());
+        contract.commit_claim(commitment.into());
+
+        // Reveal the commitment in a later block
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .block_index(11)
+            .context.clone()
+        );
+        contract.reveal_claim(sub_account(), "salt".to_string(), None);
+
+        // The committed account received the drop
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == sub_account()).unwrap();
+        assert_eq!(receipt.actions, vec![VmAction::Transfer { deposit: deposit - ACCESS_KEY_ALLOWANCE }]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Reveal does not match the commitment")]
+    fn test_reveal_claim_with_other_account() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Commit to claiming into bob's sub account
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .context.clone()
+        );
+        let commitment = env::sha256_array(&[sub_account().as_bytes(), b"salt"].concat());
+        contract.commit_claim(commitment.into());
+
+        // A front-runner replaying the salt with their own account does not match
+        contract.reveal_claim(bob(), "salt".to_string(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Drop is locked by a pending commitment")]
+    fn test_claim_while_committed() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Commit to claiming into bob's sub account
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .context.clone()
+        );
+        let commitment = env::sha256_array(&[sub_account().as_bytes(), b"salt"].concat());
+        contract.commit_claim(commitment.into());
+
+        // A plain claim with the same key is rejected while the commitment is pending
+        contract.claim(bob(), None, None);
+    }
+
+    #[test]
+    fn test_claim_and_call() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Claim and call a method on the recipient
+        let gas = Gas(50_000_000_000_000);
+        contract.claim_and_call(sub_account(), "stake".to_string(), b"{}".to_vec().into(), gas, None, None);
+
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == sub_account()).unwrap();
+        assert_eq!(receipt.actions, vec![
+            VmAction::Transfer { deposit: deposit - ACCESS_KEY_ALLOWANCE },
+            VmAction::FunctionCall {
+                function_name: "stake".to_string(),
+                args: b"{}".to_vec(),
+                gas,
+                deposit: 0,
+            },
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough prepaid gas for the call")]
+    fn test_claim_and_call_with_too_much_gas() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .prepaid_gas(Gas(100_000_000_000_000))
+            .context.clone()
+        );
+
+        // The call would leave no gas for the claim itself
+        contract.claim_and_call(sub_account(), "stake".to_string(), b"{}".to_vec().into(), Gas(90_000_000_000_000), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot call the linkdrop contract itself")]
+    fn test_claim_and_call_linkdrop() {
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        let (mut contract, pk) = send_from_bob(deposit, LinkDrop::send);
+
+        // The drop key tries to sweep the contract, with the contract as the predecessor
+        set_claim_context(&pk, deposit);
+        let args = format!("{{\"beneficiary\":\"{}\"}}", bob()).into_bytes();
+        contract.claim_and_call(linkdrop(), "sweep_unallocated".to_string(), args.into(), Gas(1), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Deposit must cover the access key allowance")]
+    fn test_send_deposit_equal_to_allowance() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain with a deposit that only covers the allowance
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+
+        // No balance would remain for the drop
+        contract.send(pk);
+    }
+
+    #[test]
+    fn test_send_deposit_above_allowance() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain with a deposit just above the allowance
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE + 1)
+            .context.clone()
+        );
+
+        // The stored balance is what is left after the allowance
+        contract.send(pk.clone());
+        assert_eq!(contract.get_key_balance(pk).unwrap().0, 1);
+    }
+
+    #[test]
+    fn test_send_two_times() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+        assert_eq!(contract.get_key_balance(pk.clone()).unwrap(), (deposit - ACCESS_KEY_ALLOWANCE).into());
+
+        // Re-initialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .account_balance(deposit)
+            .attached_deposit(deposit + 1)
+            .context.clone()
+        );
+
+        // Attempt to recreate the same linkdrop twice
+        contract.send(pk.clone());
+        assert_eq!(
+            contract.accounts.get(&pk.into()).unwrap().balance,
+            deposit + deposit + 1 - 2 * ACCESS_KEY_ALLOWANCE
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Key already has a drop")]
+    fn test_send_once_two_times() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // The first send registers the drop
+        contract.send_once(pk.clone());
+        assert_eq!(contract.get_key_balance(pk.clone()).unwrap().0, deposit - ACCESS_KEY_ALLOWANCE);
+
+        // Sending again to the same key is rejected
+        contract.send_once(pk);
+    }
+
+    #[test]
+    fn test_send_on_behalf_records_funder() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // The relayer paying for the drop
+        let relayer: AccountId = "relayer".parse().unwrap();
+        let amount = ACCESS_KEY_ALLOWANCE * 10;
+
+        // Initialize the mocked blockchain with the relayer as the predecessor
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(relayer.clone())
+            .attached_deposit(amount + ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+
+        // The relayer creates the linkdrop on behalf of bob
+        contract.send_on_behalf(bob(), pk.clone(), U128(amount));
+
+        // Bob, not the relayer, is the funder of the drop
+        assert_eq!(contract.get_key_balance(pk.clone()).unwrap().0, amount);
+        assert_eq!(contract.accounts.get(&pk).unwrap().funder_id, Some(bob()));
+        assert_eq!(contract.get_funder_stats(bob()).unwrap().created, 1);
+        assert!(contract.get_funder_stats(relayer).is_none());
+    }
+
+    #[test]
+    fn test_top_up() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk.clone());
+
+        // Top it up with another deposit
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.top_up(pk.clone()).unwrap();
+
+        // The whole top-up goes to the balance and no access key is added
+        assert_eq!(contract.get_key_balance(pk).unwrap().0, deposit * 2 - ACCESS_KEY_ALLOWANCE);
+        assert_eq!(contract.get_total_locked().0, deposit * 2 - ACCESS_KEY_ALLOWANCE);
+        assert!(get_created_receipts().is_empty());
+    }
+
+    #[test]
+    fn test_top_up_missing_key() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+
+        // Attempt to top up a key without a drop
+        assert_eq!(contract.top_up(pk), Err(LinkdropError::KeyMissing));
+    }
+
+    #[test]
+    fn test_send_batch() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create five public keys, each with its own balance
+        let pks: Vec<PublicKey> = vec![
+            "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz".parse().unwrap(),
+            "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca".parse().unwrap(),
+            "4BTYRHcT1s5WCBFdY3gn5rxsdB5tAQkTrUVLhrhDU9Vb".parse().unwrap(),
+            "7fX8Xt9MzFY6xyUuuSjpWjQvJs2xS9tjyQGUQmYXShPK".parse().unwrap(),
+            "9Fgnm3iVbSXMjzmg1ELbn5zAeUv8FqFyjLtpEoYKBH5p".parse().unwrap(),
+        ];
+        let drops: Vec<(PublicKey, U128)> = pks
+            .iter()
+            .enumerate()
+            .map(|(i, pk)| (pk.clone(), U128(ACCESS_KEY_ALLOWANCE * (i as u128 + 1))))
+            .collect();
+        // Attach an extra allowance on top of the drops to pay for storage
+        let deposit: Balance = drops.iter().map(|(_, amount)| amount.0 + ACCESS_KEY_ALLOWANCE).sum::<Balance>()
+            + ACCESS_KEY_ALLOWANCE;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create all the linkdrops at once
+        contract.send_batch(drops.clone());
+
+        for (pk, amount) in drops {
+            assert_eq!(contract.get_key_balance(pk).unwrap(), amount);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must cover all drops, their access key allowances and storage")]
+    fn test_send_batch_storage_not_covered() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain with a deposit covering only the drops and allowances
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 4)
+            .context.clone()
+        );
+
+        // Attempt to create the linkdrops without paying for their storage
+        contract.send_batch(vec![
+            (pk, U128(ACCESS_KEY_ALLOWANCE)),
+            (pk2, U128(ACCESS_KEY_ALLOWANCE)),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate public key in batch")]
+    fn test_send_batch_duplicate_key() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 4)
+            .context.clone()
+        );
+
+        // Attempt to register the same key twice in one batch
+        contract.send_batch(vec![
+            (pk.clone(), U128(ACCESS_KEY_ALLOWANCE)),
+            (pk, U128(ACCESS_KEY_ALLOWANCE)),
+        ]);
+    }
+
+    #[test]
+    fn test_total_locked_across_send_and_claim() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create two linkdrops
+        contract.send(pk.clone());
+        contract.send(pk2.clone());
+        assert_eq!(contract.get_total_locked().0, 2 * (deposit - ACCESS_KEY_ALLOWANCE));
+
+        // Claim the first one
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit * 2)
+            .context.clone()
+        );
+        contract.claim(bob(), None, None);
+        assert_eq!(contract.get_total_locked().0, deposit - ACCESS_KEY_ALLOWANCE);
+
+        // Claim the second one into a new account
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk2)
+            .account_balance(deposit)
+            .context.clone()
+        );
+        let new_pk: PublicKey = "4BTYRHcT1s5WCBFdY3gn5rxsdB5tAQkTrUVLhrhDU9Vb"
+            .parse()
+            .unwrap();
+        contract.create_account_and_claim(sub_account(), new_pk, None, None).unwrap();
+        assert_eq!(contract.get_total_locked().0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Total locked balance overflow")]
+    fn test_total_locked_overflow_panics() {
+        // Create a new instance of the linkdrop contract close to the limit
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        contract.total_locked = u128::MAX - 1;
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+
+        // Pushing the total past u128::MAX must panic
+        contract.send(pk);
+    }
+
+    #[test]
+    fn test_total_locked_saturates_on_claim() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Normal operation tracks the exact amount
+        contract.send(pk.clone());
+        assert_eq!(contract.get_total_locked().0, deposit - ACCESS_KEY_ALLOWANCE);
+
+        // Simulate a counter that drifted below the drop balance
+        contract.total_locked = 1;
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+        contract.claim(bob(), None, None);
+        assert_eq!(contract.get_total_locked().0, 0);
+    }
+
+    #[test]
+    fn test_monitoring_snapshot() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        let pk3: PublicKey = "4BTYRHcT1s5WCBFdY3gn5rxsdB5tAQkTrUVLhrhDU9Vb"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        let balance = deposit - ACCESS_KEY_ALLOWANCE;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create three linkdrops
+        contract.send(pk.clone());
+        contract.send(pk2.clone());
+        contract.send(pk3);
+
+        // Claim the first one in full and part of the second one
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit * 4)
+            .context.clone()
+        );
+        contract.claim(bob(), None, None);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk2)
+            .account_balance(deposit * 4)
+            .context.clone()
+        );
+        contract.claim_amount(bob(), U128(1000), None);
+
+        // The snapshot reflects the outstanding drops and the lifetime claims
+        let snapshot = contract.get_monitoring_snapshot();
+        assert_eq!(snapshot.drops, 2);
+        assert_eq!(snapshot.total_locked.0, 2 * balance - 1000);
+        assert_eq!(snapshot.claimed_count, 2);
+        assert_eq!(snapshot.claimed_amount.0, balance + 1000);
+        assert_eq!(snapshot.owner_id, linkdrop());
+    }
+
+    #[test]
+    fn test_sweep_unallocated_keeps_live_drops() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        // Dust held by the contract on top of the drop and its storage
+        let surplus = 12_345;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(pk);
+
+        // The owner sweeps while the drop is still live
+        let storage_staked = Balance::from(env::storage_usage()) * env::storage_byte_cost();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .account_balance(storage_staked + deposit + surplus)
+            .storage_usage(env::storage_usage())
+            .context.clone()
+        );
+        contract.sweep_unallocated(bob());
+
+        // Only the surplus is transferred
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, bob());
+        assert_eq!(receipts[0].actions, vec![VmAction::Transfer { deposit: surplus }]);
+    }
+
+    #[test]
+    fn test_funder_stats() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain with bob as the funder
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        assert!(contract.get_funder_stats(bob()).is_none());
+
+        // Create two linkdrops and top up the first one
+        contract.send(pk.clone());
+        contract.send(pk2);
+        contract.send(pk.clone());
+
+        // Claim the first one
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit * 4)
+            .context.clone()
+        );
+        contract.claim(sub_account(), None, None);
+
+        // Top-ups are not counted as new drops
+        let stats = contract.get_funder_stats(bob()).unwrap();
+        assert_eq!(stats.created, 2);
+        assert_eq!(stats.claimed, 1);
+        assert_eq!(stats.reclaimed, 0);
+    }
+
+    #[test]
+    fn test_get_drops_by_funder() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create three public keys for bob and one for another funder
+        let bob_pks: Vec<PublicKey> = vec![
+            "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz".parse().unwrap(),
+            "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca".parse().unwrap(),
+            "4BTYRHcT1s5WCBFdY3gn5rxsdB5tAQkTrUVLhrhDU9Vb".parse().unwrap(),
+        ];
+        let other_pk: PublicKey = "7fX8Xt9MzFY6xyUuuSjpWjQvJs2xS9tjyQGUQmYXShPK"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        let balance = U128(deposit - ACCESS_KEY_ALLOWANCE);
+
+        // Bob funds three linkdrops
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        for pk in bob_pks.iter() {
+            contract.send(pk.clone());
+        }
+
+        // Another funder funds one
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(sub_account())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.send(other_pk.clone());
+
+        // Each funder only sees their own drops, page by page
+        let first_page = contract.get_drops_by_funder(bob(), 0, 2);
+        let second_page = contract.get_drops_by_funder(bob(), 2, 2);
+        assert_eq!(first_page, vec![(bob_pks[0].clone(), balance), (bob_pks[1].clone(), balance)]);
+        assert_eq!(second_page, vec![(bob_pks[2].clone(), balance)]);
+        assert_eq!(contract.get_drops_by_funder(sub_account(), 0, 10), vec![(other_pk, balance)]);
+        assert!(contract.get_drops_by_funder(linkdrop(), 0, 10).is_empty());
+
+        // Claimed drops leave the funder's list
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(bob_pks[0].clone())
+            .account_balance(deposit * 4)
+            .context.clone()
+        );
+        contract.claim(sub_account(), None, None);
+        let remaining: Vec<PublicKey> = contract
+            .get_drops_by_funder(bob(), 0, 10)
+            .into_iter()
+            .map(|(pk, _)| pk)
+            .collect();
+        assert_eq!(remaining.len(), 2);
+        assert!(!remaining.contains(&bob_pks[0]));
+    }
+
+    #[test]
+    fn test_contract_metadata() {
+        // Create a new instance of the linkdrop contract
+        testing_env!(VMContextBuilder::new().current_account_id(linkdrop()).context.clone());
+        let contract = LinkDrop::new(linkdrop(), None);
+
+        // The metadata reports the crate version and the access key allowance
+        let metadata = contract.contract_metadata();
+        assert_eq!(metadata.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(metadata.standards, vec!["linkdrop-1.0.0".to_string()]);
+        assert_eq!(metadata.access_key_allowance.0, ACCESS_KEY_ALLOWANCE);
+    }
+
+    #[test]
+    fn test_create_advanced_account() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to an extremely small amount
+        let deposit = 1_000_000;
+
+        // Create options for the advanced account creation
+        let options: CreateAccountOptions = CreateAccountOptions {
+            full_access_keys: Some(vec![pk.clone()]),
+            limited_access_keys: Some(vec![LimitedAccessKey {
+                public_key: pk2,
+                allowance: U128(100),
+                receiver_id: linkdrop(),
+                method_names: "send".to_string(),
+            }]),
+            contract_bytes: Some(include_bytes!("../target/wasm32-unknown-unknown/release/linkdrop.wasm").to_vec()),
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        };
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create bob's account with the advanced options
+        contract.create_account_advanced(sub_account(), options);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate public key across access keys")]
+    fn test_create_advanced_account_with_duplicate_keys() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to an extremely small amount
+        let deposit = 1_000_000;
+
+        // Create options for the advanced account creation
+        let options: CreateAccountOptions = CreateAccountOptions {
+            full_access_keys: Some(vec![pk.clone()]),
+            limited_access_keys: Some(vec![LimitedAccessKey {
+                public_key: pk.clone(),
+                allowance: U128(100),
+                receiver_id: linkdrop(),
+                method_names: "send".to_string(),
+            }]),
+            contract_bytes: Some(include_bytes!("../target/wasm32-unknown-unknown/release/linkdrop.wasm").to_vec()),
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        };
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // The same key as a full access key and a limited access key is rejected up front
+        contract.create_account_advanced(sub_account(), options);
+    }
+
+    #[test]
+    fn test_create_advanced_account_with_method_name_list() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(1_000_000)
+            .context.clone()
+        );
+
+        // Create bob's account with a limited key allowed to call two methods
+        contract.create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: None,
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: Some(vec![LimitedAccessKeyV2 {
+                public_key: pk.clone(),
+                allowance: U128(100),
+                receiver_id: linkdrop(),
+                method_names: vec!["send".to_string(), "claim".to_string()],
+            }]),
+            stake: None,
+            key_labels: None
+        });
+
+        // The key is added with both method names
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == sub_account()).unwrap();
+        assert!(receipt.actions.contains(&VmAction::AddKeyWithFunctionCall {
+            public_key: pk,
+            nonce: 0,
+            allowance: Some(100),
+            receiver_id: linkdrop(),
+            function_names: vec!["send".to_string(), "claim".to_string()],
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Method names cannot be empty")]
+    fn test_create_advanced_account_with_empty_method_name() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(1_000_000)
+            .context.clone()
+        );
+
+        // An empty entry in the list is rejected
+        contract.create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: None,
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: Some(vec![LimitedAccessKeyV2 {
+                public_key: pk,
+                allowance: U128(100),
+                receiver_id: linkdrop(),
+                method_names: vec!["send".to_string(), "".to_string()],
+            }]),
+            stake: None,
+            key_labels: None
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract bytes cannot be empty")]
+    fn test_create_advanced_account_with_empty_contract_bytes() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(1_000_000)
+            .context.clone()
+        );
+
+        // Empty code is rejected before anything is deployed
+        contract.create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: None,
+            limited_access_keys: None,
+            contract_bytes: Some(vec![]),
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        });
+    }
+
+    #[test]
+    fn test_create_advanced_account_with_stake() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let validator_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create bob's account and stake half of the deposit
+        contract.create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: Some(vec![pk]),
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: Some((validator_pk.clone(), U128(deposit / 2))),
+            key_labels: None
+        });
+
+        // The stake action is part of the account creation
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == sub_account()).unwrap();
+        assert_eq!(
+            receipt.actions.last(),
+            Some(&VmAction::Stake { stake: deposit / 2, public_key: validator_pk })
+        );
+    }
+
+    #[test]
+    fn test_create_advanced_account_with_key_labels() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let limited_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create bob's account with a labeled full access key and a labeled function call key
+        contract.create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: Some(vec![pk.clone()]),
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: Some(vec![LimitedAccessKeyV2 {
+                public_key: limited_pk.clone(),
+                allowance: U128(100),
+                receiver_id: bob(),
+                method_names: vec!["send".to_string()],
+            }]),
+            stake: None,
+            key_labels: Some(vec![
+                (pk.clone(), "recovery".to_string()),
+                (limited_pk.clone(), "game session".to_string()),
+            ])
+        });
+
+        // Nothing is recorded until the account exists
+        assert_eq!(contract.get_key_label(sub_account(), pk.clone()), None);
+        let args = take_create_account_callback(PromiseResult::Successful(vec![]));
+        let advanced = serde_json::from_value(args["advanced"].clone()).unwrap();
+        contract.on_account_created(sub_account(), linkdrop(), U128(deposit), None, 0, advanced);
+
+        // Both labels resolve for the new account only
+        assert_eq!(contract.get_key_label(sub_account(), pk.clone()), Some("recovery".to_string()));
+        assert_eq!(contract.get_key_label(sub_account(), limited_pk), Some("game session".to_string()));
+        assert_eq!(contract.get_key_label(bob(), pk), None);
+    }
+
+    #[test]
+    fn test_estimate_required_deposit() {
+        // Create a new instance of the linkdrop contract
+        let contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .context.clone()
+        );
+
+        // Options adding a single key, without and with a contract
+        let options = |contract_bytes: Option<Vec<u8>>| CreateAccountOptions {
+            full_access_keys: Some(vec![pk.clone()]),
+            limited_access_keys: None,
+            contract_bytes,
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        };
+        let without_contract = contract.estimate_required_deposit(options(None)).0;
+        let with_contract = contract.estimate_required_deposit(options(Some(vec![0u8; 500]))).0;
+
+        // The account and its key are always paid for, and the contract costs its length in storage
+        assert_eq!(
+            without_contract,
+            Balance::from(NEW_ACCOUNT_STORAGE_BYTES + ACCESS_KEY_STORAGE_BYTES) * env::storage_byte_cost()
+        );
+        assert_eq!(with_contract - without_contract, 500 * env::storage_byte_cost());
+    }
+
+    #[test]
+    fn test_preview_create_advanced_account_all_clear() {
+        // Create a new instance of the linkdrop contract
+        let contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .context.clone()
+        );
+
+        // A valid payload with a generous deposit raises no warning
+        let warnings = contract.preview_create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: Some(vec![pk]),
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        }, U128(ACCESS_KEY_ALLOWANCE * 100));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_preview_create_advanced_account_underfunded() {
+        // Create a new instance of the linkdrop contract
+        let contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .context.clone()
+        );
+
+        // A deposit too small for the storage of the new account and its contract
+        let contract_bytes = vec![0u8; 100];
+        let required = Balance::from(NEW_ACCOUNT_STORAGE_BYTES + ACCESS_KEY_STORAGE_BYTES + 100) * env::storage_byte_cost();
+        let warnings = contract.preview_create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: Some(vec![pk]),
+            limited_access_keys: None,
+            contract_bytes: Some(contract_bytes),
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        }, U128(1));
+        assert_eq!(warnings, vec![PreviewWarning::InsufficientDeposit { required: required.into() }]);
+    }
+
+    #[test]
+    fn test_preview_create_advanced_account_invalid_name_and_options() {
+        // Create a new instance of the linkdrop contract
+        let contract = LinkDrop::new(linkdrop(), None);
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .context.clone()
+        );
+
+        // A sibling account and no keys nor contract
+        let sibling: AccountId = "bob.near".parse().unwrap();
+        let warnings = contract.preview_create_account_advanced(sibling, CreateAccountOptions {
+            full_access_keys: None,
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        }, U128(ACCESS_KEY_ALLOWANCE * 100));
+        assert_eq!(warnings, vec![
+            PreviewWarning::CannotCreateAccount,
+            PreviewWarning::InvalidOptions(LinkdropError::NoCreateOptions.as_ref().to_string()),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Stake exceeds the attached deposit minus the storage reserve")]
+    fn test_create_advanced_account_with_stake_over_deposit() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let validator_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Staking the whole deposit leaves nothing for storage
+        contract.create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: Some(vec![pk]),
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: Some((validator_pk, U128(deposit))),
+            key_labels: None
+        });
+    }
+
+    #[test]
+    fn test_create_advanced_account_with_callback_gas() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Gas requested for the callback
+        let callback_gas = Gas(30_000_000_000_000);
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(1_000_000)
+            .context.clone()
+        );
+
+        // Create bob's account with a custom callback gas
+        contract.create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: Some(vec![pk]),
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: None,
+            callback_gas: Some(callback_gas),
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        });
+
+        // The callback is scheduled with the requested gas
+        let receipts = get_created_receipts();
+        let callback = receipts.iter().find(|receipt| receipt.receiver_id == linkdrop()).unwrap();
+        match &callback.actions[0] {
+            VmAction::FunctionCall { function_name, gas, .. } => {
+                assert_eq!(function_name, "on_account_created");
+                assert_eq!(*gas, callback_gas);
+            }
+            action => panic!("Unexpected action {:?}", action),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Callback gas must be between MIN_CALLBACK_GAS and MAX_CALLBACK_GAS")]
+    fn test_create_advanced_account_with_too_much_callback_gas() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(1_000_000)
+            .context.clone()
+        );
+
+        // Request more callback gas than allowed
+        contract.create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: Some(vec![pk]),
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: None,
+            callback_gas: Some(Gas(MAX_CALLBACK_GAS.0 + 1)),
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        });
+    }
+
+    #[test]
+    fn test_create_advanced_account_emits_summary_event() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to an extremely small amount
+        let deposit = 1_000_000;
+        let contract_bytes = include_bytes!("../target/wasm32-unknown-unknown/release/linkdrop.wasm").to_vec();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create bob's account with one key of each kind and a contract
+        contract.create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: Some(vec![pk]),
+            limited_access_keys: Some(vec![LimitedAccessKey {
+                public_key: pk2,
+                allowance: U128(100),
+                receiver_id: linkdrop(),
+                method_names: "send".to_string(),
+            }]),
+            contract_bytes: Some(contract_bytes.clone()),
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        });
+
+        // Nothing is emitted until the account exists
+        assert!(get_logs().is_empty());
+        let args = take_create_account_callback(PromiseResult::Successful(vec![]));
+        let advanced = serde_json::from_value(args["advanced"].clone()).unwrap();
+        assert!(contract.on_account_created(sub_account(), linkdrop(), U128(deposit), None, 0, advanced));
+
+        let logs = get_logs();
+        assert_eq!(logs.len(), 1);
+        let event: serde_json::Value = serde_json::from_str(logs[0].strip_prefix("EVENT_JSON:").unwrap()).unwrap();
+        let contract_hash: Base58CryptoHash = env::sha256_array(&contract_bytes).into();
+        assert_eq!(event, serde_json::json!({
+            "standard": "linkdrop",
+            "version": "1.0.0",
+            "event": "create_account_advanced",
+            "data": [{
+                "account_id": sub_account(),
+                "full_access_keys": 1,
+                "limited_access_keys": 1,
+                "contract_hash": contract_hash,
+                "amount": U128(deposit),
+            }]
+        }));
+    }
+
+    #[test]
+    fn test_create_advanced_account_with_base64_contract_byte_string() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+
+        // Default the deposit to an extremely small amount
+        let deposit = 1_000_000;
+
+        // Create options for the advanced account creation
+        let options: CreateAccountOptions = CreateAccountOptions {
+            full_access_keys: None,
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: Some(include_bytes!("../target/wasm32-unknown-unknown/release/linkdrop.wasm").to_vec().into()),
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        };
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create bob's account with the advanced options
+        contract.create_account_advanced(sub_account(), options);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_create_advanced_account_no_options() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Default the deposit to an extremely small amount
+        let deposit = 1_000_000;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create bob's account with the advanced options
+        contract.create_account_advanced(sub_account(), CreateAccountOptions { full_access_keys: None, limited_access_keys: None, contract_bytes: None, contract_bytes_base64: None, callback_gas: None, limited_access_keys_v2: None, stake: None, key_labels: None });
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_create_advanced_account_conflict_contract_bytes() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Default the deposit to an extremely small amount
+        let deposit = 1_000_000;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create bob's account with the advanced options
+        contract.create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: None,
+            limited_access_keys: None,
+            contract_bytes: Some(include_bytes!("../target/wasm32-unknown-unknown/release/linkdrop.wasm").to_vec()),
+            contract_bytes_base64: Some(include_bytes!("../target/wasm32-unknown-unknown/release/linkdrop.wasm").to_vec().into()),
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        });
+    }
+
+    #[test]
+    fn test_balance_breakdown_reflects_freed_storage() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pks: Vec<PublicKey> = vec![
+            "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz".parse().unwrap(),
+            "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca".parse().unwrap(),
+            "4BTYRHcT1s5WCBFdY3gn5rxsdB5tAQkTrUVLhrhDU9Vb".parse().unwrap(),
+        ];
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrops
+        for pk in pks.iter() {
+            contract.send(pk.clone());
+        }
+
+        for pk in pks {
+            // Claim each drop with its own key
+            testing_env!(
+                VMContextBuilder::new()
+                .current_account_id(linkdrop())
+                .predecessor_account_id(linkdrop())
+                .signer_account_pk(pk.clone())
+                .account_balance(deposit * 4)
+                .context.clone()
+            );
+
+            let before = contract.get_balance_breakdown();
+            contract.claim(bob(), None, None);
+            let after = contract.get_balance_breakdown();
+
+            // The freed storage shows up in the available balance
+            let freed = before.storage_staked.0 - after.storage_staked.0;
+            assert!(freed > 0);
+            assert_eq!(after.locked.0, before.locked.0 - (deposit - ACCESS_KEY_ALLOWANCE));
+            assert_eq!(after.available.0, before.available.0 + freed);
+        }
+    }
+
+    #[test]
+    fn test_admin_can_create_advanced_account() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // The owner adds bob as an admin
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.add_admin(bob()).unwrap();
+        assert_eq!(contract.get_admins(), vec![bob()]);
+
+        // Bob can now create advanced accounts
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(1_000_000)
+            .context.clone()
+        );
+        contract.create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: Some(vec![pk]),
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        });
+
+        // Once removed, bob is no longer an admin
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.remove_admin(bob()).unwrap();
+        assert!(contract.get_admins().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner or an admin can create advanced accounts")]
+    fn test_non_admin_cannot_create_advanced_account() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain with a predecessor that is not an admin
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(1_000_000)
+            .context.clone()
+        );
+        contract.create_account_advanced(sub_account(), CreateAccountOptions {
+            full_access_keys: Some(vec![pk]),
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        });
+    }
+
+    #[test]
+    fn test_only_owner_can_add_admin() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+
+        // Initialize the mocked blockchain with a predecessor that is not the owner
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        assert_eq!(contract.add_admin(bob()), Err(LinkdropError::Unauthorized));
+        assert!(contract.get_admins().is_empty());
+    }
+
+    #[test]
+    fn test_send_uses_default_claim_methods() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let custom_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+
+        // A drop created with the built-in methods
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+        assert_eq!(contract.get_key_methods(pk.clone()), Some(ACCESS_KEY_METHOD_NAMES.split(',').map(String::from).collect()));
+
+        // The owner narrows the methods down and creates another drop
+        contract.set_default_claim_methods("claim,claim_and_call".to_string());
+        contract.send(custom_pk.clone());
+
+        // Only the new key gets the configured methods
+        assert_eq!(contract.contract_metadata().default_claim_methods, "claim,claim_and_call");
+        let methods = vec!["claim".to_string(), "claim_and_call".to_string()];
+        assert_eq!(contract.get_key_methods(custom_pk.clone()), Some(methods.clone()));
+        assert_eq!(contract.get_key_methods(pk), Some(ACCESS_KEY_METHOD_NAMES.split(',').map(String::from).collect()));
+        let receipts = get_created_receipts();
+        assert!(receipts
+            .iter()
+            .flat_map(|receipt| receipt.actions.iter())
+            .any(|action| *action == VmAction::AddKeyWithFunctionCall {
+                public_key: custom_pk.clone(),
+                nonce: 0,
+                allowance: Some(ACCESS_KEY_ALLOWANCE),
+                receiver_id: linkdrop(),
+                function_names: methods.clone(),
+            }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Method names cannot be empty")]
+    fn test_set_default_claim_methods_empty() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.set_default_claim_methods("claim,".to_string());
+    }
+
+    #[test]
+    fn test_send_uses_updated_access_key_allowance() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // New allowance set by the owner
+        let allowance = ACCESS_KEY_ALLOWANCE / 2;
+
+        // The owner lowers the allowance
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.set_access_key_allowance(U128(allowance));
+        assert_eq!(contract.contract_metadata().access_key_allowance.0, allowance);
+
+        // Create the linkdrop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+
+        // Only the new allowance is taken from the deposit and given to the key
+        assert_eq!(contract.get_key_balance(pk.clone()).unwrap().0, ACCESS_KEY_ALLOWANCE - allowance);
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].actions, vec![VmAction::AddKeyWithFunctionCall {
+            public_key: pk,
+            nonce: 0,
+            allowance: Some(allowance),
+            receiver_id: linkdrop(),
+            function_names: ACCESS_KEY_METHOD_NAMES.split(',').map(String::from).collect(),
+        }]);
+    }
+
+    #[test]
+    fn test_zero_access_key_allowance_is_unlimited() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // The owner removes the allowance cap
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.set_access_key_allowance(U128(0));
+
+        // Create the linkdrop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(1_000)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+
+        // The whole deposit goes to the drop and the key has no allowance limit
+        assert_eq!(contract.get_key_balance(pk).unwrap().0, 1_000);
+        let receipts = get_created_receipts();
+        match &receipts[0].actions[0] {
+            VmAction::AddKeyWithFunctionCall { allowance, .. } => assert_eq!(*allowance, None),
+            action => panic!("Unexpected action {:?}", action),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this method")]
+    fn test_only_owner_can_set_access_key_allowance() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+
+        // Initialize the mocked blockchain with a predecessor that is not the owner
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.set_access_key_allowance(U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_paused_contract_rejects_send() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // The owner pauses the contract
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.set_paused(true);
+
+        // Views still work, but sends are rejected
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        assert_eq!(contract.get_total_locked().0, 0);
+        contract.send(pk);
+    }
+
+    #[test]
+    fn test_owner_can_unpause() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // The owner pauses and then unpauses the contract
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.set_paused(true);
+        assert!(contract.get_monitoring_snapshot().paused);
+        contract.set_paused(false);
+
+        // Sends work again
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+        assert_eq!(contract.get_key_balance(pk).unwrap().0, ACCESS_KEY_ALLOWANCE);
+    }
+
+    #[test]
+    fn test_custom_storage_prefix() {
+        // Create a new instance of the linkdrop contract under a tenant prefix
+        let mut contract = LinkDrop::new(linkdrop(), Some(b"tenant1".to_vec()));
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop and read it back
+        contract.send(pk.clone());
+        assert_eq!(contract.get_key_balance(pk).unwrap().0, deposit - ACCESS_KEY_ALLOWANCE);
+
+        // A contract with the default prefix doesn't see the tenant's drops
+        let other = LinkDrop::new(linkdrop(), None);
+        assert_eq!(other.accounts.len(), 0);
+    }
+
+    #[test]
+    fn test_registrar_can_create_top_level_account() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain with the contract deployed as the registrar
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(REGISTRAR_ACCOUNT_ID.parse().unwrap())
+            .attached_deposit(1_000_000)
+            .context.clone()
+        );
+
+        // Create a top-level account
+        contract.create_account(bob(), pk);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot create this account name")]
+    fn test_cannot_create_sibling_account() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id("linkdrop.near".parse().unwrap())
+            .attached_deposit(1_000_000)
+            .context.clone()
+        );
+
+        // Attempt to create an account next to the contract rather than under it
+        contract.create_account("bob.near".parse().unwrap(), pk);
+    }
+
+    #[test]
+    fn test_rotate_key() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the old and new public keys to be used in the test
+        let old_pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let new_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain with bob as the funder
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop
+        contract.send(old_pk.clone());
+
+        // Bob moves the drop to a new key, paying for its allowance
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+        contract.rotate_key(old_pk.clone(), new_pk.clone());
+
+        // The balance now belongs to the new key and the totals are unchanged
+        assert!(contract.get_key_information(old_pk.clone()).is_err());
+        assert_eq!(contract.get_key_balance(new_pk.clone()).unwrap().0, deposit - ACCESS_KEY_ALLOWANCE);
+        assert_eq!(contract.get_total_locked().0, deposit - ACCESS_KEY_ALLOWANCE);
+
+        // The old access key is deleted and the new one is added with the same allowance
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].actions, vec![
+            VmAction::DeleteKey { public_key: old_pk },
+            VmAction::AddKeyWithFunctionCall {
+                public_key: new_pk,
+                nonce: 0,
+                allowance: Some(ACCESS_KEY_ALLOWANCE),
+                receiver_id: linkdrop(),
+                function_names: ACCESS_KEY_METHOD_NAMES.split(',').map(String::from).collect(),
+            },
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "New public key already has a drop")]
+    fn test_rotate_key_to_existing_drop() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain with bob as the funder
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create two linkdrops
+        contract.send(pk.clone());
+        contract.send(pk2.clone());
+
+        // Attempt to move the first drop onto the second one
+        contract.rotate_key(pk, pk2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must equal the allowance of the new key")]
+    fn test_rotate_key_without_deposit() {
+        let (mut contract, pk) = send_from_bob(ACCESS_KEY_ALLOWANCE * 2, LinkDrop::send);
+        let new_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+
+        // The contract would pay for a fresh allowance on every rotation
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.rotate_key(pk, new_pk);
+    }
+
+    #[test]
+    fn test_cancel_forfeits_allowance() {
+        let (mut contract, pk) = send_from_bob(ACCESS_KEY_ALLOWANCE * 2, LinkDrop::send);
+
+        // Bob cancels the drop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .account_balance(ACCESS_KEY_ALLOWANCE * 2)
+            .storage_usage(env::storage_usage())
+            .context.clone()
+        );
+        assert_eq!(contract.cancel_many(vec![pk]), 1);
+
+        // Only the drop balance is refunded and the allowance is no longer reserved
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == bob()).unwrap();
+        assert_eq!(receipt.actions, vec![VmAction::Transfer { deposit: ACCESS_KEY_ALLOWANCE }]);
+        assert_eq!(contract.locked_allowances, 0);
+
+        // The owner can sweep the allowance
+        let storage_staked = Balance::from(env::storage_usage()) * env::storage_byte_cost();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .account_balance(storage_staked + ACCESS_KEY_ALLOWANCE)
+            .storage_usage(env::storage_usage())
+            .context.clone()
+        );
+        contract.sweep_unallocated(sub_account());
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, sub_account());
+        assert_eq!(receipts[0].actions, vec![VmAction::Transfer { deposit: ACCESS_KEY_ALLOWANCE }]);
+    }
+
+    #[test]
+    fn test_cancel_many() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        let other_pk: PublicKey = "4BTYRHcT1s5WCBFdY3gn5rxsdB5tAQkTrUVLhrhDU9Vb"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Bob funds two linkdrops
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+        contract.send(pk2.clone());
+
+        // Someone else funds a third one
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(sub_account())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.send(other_pk.clone());
+
+        // Bob cancels his drops along with the one he didn't fund
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .account_balance(deposit * 3)
+            .context.clone()
+        );
+        let cancelled = contract.cancel_many(vec![pk.clone(), pk2.clone(), other_pk.clone()]);
+
+        // Only bob's drops are removed and the other one is untouched
+        assert_eq!(cancelled, 2);
+        assert!(contract.get_key_information(pk.clone()).is_err());
+        assert!(contract.get_key_information(pk2.clone()).is_err());
+        assert_eq!(contract.get_key_balance(other_pk).unwrap().0, deposit - ACCESS_KEY_ALLOWANCE);
+        assert_eq!(contract.get_total_locked().0, deposit - ACCESS_KEY_ALLOWANCE);
+        assert_eq!(contract.get_funder_stats(bob()).unwrap().reclaimed, 2);
+
+        // Both access keys are deleted and bob gets both balances back in one transfer
+        let receipts = get_created_receipts();
+        let actions_to = |account_id: AccountId| {
+            receipts
+                .iter()
+                .find(|receipt| receipt.receiver_id == account_id)
+                .map(|receipt| receipt.actions.clone())
+        };
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(
+            actions_to(linkdrop()),
+            Some(vec![
+                VmAction::DeleteKey { public_key: pk },
+                VmAction::DeleteKey { public_key: pk2 },
+            ])
+        );
+        assert_eq!(
+            actions_to(bob()),
+            Some(vec![VmAction::Transfer { deposit: 2 * (deposit - ACCESS_KEY_ALLOWANCE) }])
+        );
+    }
+
+    #[test]
+    fn test_send_advanced() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Balance of the drop and a larger allowance to deploy a contract on claim
+        let balance = ACCESS_KEY_ALLOWANCE * 10;
+        let allowance = ACCESS_KEY_ALLOWANCE * 5;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(balance + allowance)
+            .context.clone()
+        );
+
+        // Create the linkdrop only allowing create_account_and_claim
+        contract.send_advanced(
+            pk.clone(),
+            U128(balance),
+            U128(allowance),
+            "create_account_and_claim".to_string(),
+        );
+
+        // The drop holds the whole balance and the key gets the given allowance and method
+        assert_eq!(contract.get_key_balance(pk.clone()).unwrap().0, balance);
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].actions, vec![VmAction::AddKeyWithFunctionCall {
+            public_key: pk,
+            nonce: 0,
+            allowance: Some(allowance),
+            receiver_id: linkdrop(),
+            function_names: vec!["create_account_and_claim".to_string()],
+        }]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Drop keys can only call claim methods")]
+    fn test_send_advanced_with_owner_method() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let balance = ACCESS_KEY_ALLOWANCE * 10;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(balance + ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+
+        // Attempt to give the key an owner-only method of the contract
+        contract.send_advanced(pk, U128(balance), U128(ACCESS_KEY_ALLOWANCE), "claim,add_admin".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Allowance must be greater than zero")]
+    fn test_send_advanced_with_unlimited_allowance() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let balance = ACCESS_KEY_ALLOWANCE * 10;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(balance)
+            .context.clone()
+        );
+
+        // Attempt to create a key that can burn the contract's balance on gas
+        contract.send_advanced(pk, U128(balance), U128(0), "claim".to_string());
+    }
+
+    #[test]
+    fn test_get_key_methods() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let default_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        let balance = ACCESS_KEY_ALLOWANCE * 10;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(balance + ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+
+        // One drop with custom methods and one with the default ones
+        contract.send_advanced(
+            pk.clone(),
+            U128(balance),
+            U128(ACCESS_KEY_ALLOWANCE),
+            "claim,create_account_and_claim".to_string(),
+        );
+        contract.send(default_pk.clone());
+
+        // The methods are read back as a list
+        assert_eq!(
+            contract.get_key_methods(pk),
+            Some(vec!["claim".to_string(), "create_account_and_claim".to_string()])
+        );
+        assert_eq!(
+            contract.get_key_methods(default_pk),
+            Some(ACCESS_KEY_METHOD_NAMES.split(',').map(String::from).collect())
+        );
+
+        // Keys without a drop have none
+        let missing_pk: PublicKey = "4BTYRHcT1s5WCBFdY3gn5rxsdB5tAQkTrUVLhrhDU9Vb"
+            .parse()
+            .unwrap();
+        assert_eq!(contract.get_key_methods(missing_pk), None);
+    }
+
+    #[test]
+    fn test_reclaim_batch() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create bob's public keys and one funded by someone else
+        let pks: Vec<PublicKey> = vec![
+            "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz".parse().unwrap(),
+            "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca".parse().unwrap(),
+            "4BTYRHcT1s5WCBFdY3gn5rxsdB5tAQkTrUVLhrhDU9Vb".parse().unwrap(),
+        ];
+        let other_pk: PublicKey = "7fX8Xt9MzFY6xyUuuSjpWjQvJs2xS9tjyQGUQmYXShPK"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Bob funds three linkdrops
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        for pk in pks.iter() {
+            contract.send(pk.clone());
+        }
+
+        // Someone else funds a fourth one
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(sub_account())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.send(other_pk.clone());
+
+        // Bob reclaims all of them at the end of the campaign
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .account_balance(deposit * 4)
+            .context.clone()
+        );
+        let mut keys = pks.clone();
+        keys.push(other_pk.clone());
+        let skipped = contract.reclaim_batch(keys);
+
+        // Only the key bob didn't fund is skipped and left in place
+        assert_eq!(skipped, vec![other_pk.clone()]);
+        for pk in pks {
+            assert!(!contract.is_claimable(pk));
+        }
+        assert!(contract.is_claimable(other_pk));
+
+        // Bob gets the three balances back in one transfer
+        let receipts = get_created_receipts();
+        let refund = receipts.iter().find(|receipt| receipt.receiver_id == bob()).unwrap();
+        assert_eq!(refund.actions, vec![VmAction::Transfer { deposit: 3 * (deposit - ACCESS_KEY_ALLOWANCE) }]);
+    }
+
+    #[test]
+    fn test_send_with_fee() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // The owner sets a 2.5% fee
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.set_fee_basis_points(250);
+
+        // Create the linkdrop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+
+        // The fee is taken from the deposit along with the allowance
+        let fee = deposit / 40;
+        assert_eq!(contract.get_key_balance(pk).unwrap().0, deposit - ACCESS_KEY_ALLOWANCE - fee);
+        assert_eq!(contract.owner_fees_collected, fee);
+
+        // The owner withdraws the fees
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .account_balance(deposit)
+            .context.clone()
+        );
+        contract.withdraw_fees(bob());
+        assert_eq!(contract.owner_fees_collected, 0);
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, bob());
+        assert_eq!(receipts[0].actions, vec![VmAction::Transfer { deposit: fee }]);
+    }
+
+    #[test]
+    fn test_top_up_and_batch_pay_fee() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let batch_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // The owner sets a 2.5% fee
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.set_fee_basis_points(250);
+
+        // A small send followed by a large top up pays the fee on the top up too
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+        let sent_fee = contract.owner_fees_collected;
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.top_up(pk).unwrap();
+        assert_eq!(contract.owner_fees_collected, sent_fee + deposit / 40);
+
+        // A batch pays the fee on each of its drops
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit * 2)
+            .context.clone()
+        );
+        contract.send_batch(vec![(batch_pk.clone(), U128(deposit - ACCESS_KEY_ALLOWANCE))]);
+        assert_eq!(contract.owner_fees_collected, sent_fee + deposit / 40 * 2);
+        assert_eq!(contract.get_key_balance(batch_pk).unwrap().0, deposit - ACCESS_KEY_ALLOWANCE - deposit / 40);
+    }
+
+    #[test]
+    fn test_send_without_fee() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the linkdrop with the default zero fee
+        contract.send(pk.clone());
+
+        // Only the allowance is taken from the deposit
+        assert_eq!(contract.get_key_balance(pk).unwrap().0, deposit - ACCESS_KEY_ALLOWANCE);
+        assert_eq!(contract.owner_fees_collected, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Fee exceeds MAX_FEE_BASIS_POINTS")]
+    fn test_set_fee_above_max() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+
+        // Initialize the mocked blockchain with the owner as the predecessor
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+
+        // Attempt to set a fee above 10%
+        contract.set_fee_basis_points(MAX_FEE_BASIS_POINTS + 1);
+    }
+
+    #[test]
+    fn test_reserved_pattern_allows_other_names() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // The owner reserves names starting with "admin"
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+        contract.add_reserved_pattern("admin*".to_string());
+
+        // Names that don't match are still created
+        let name: AccountId = "bob-admin.linkdrop".parse().unwrap();
+        contract.create_account(name.clone(), pk);
+        let receipts = get_created_receipts();
+        assert_eq!(receipts[0].receiver_id, name);
+    }
+
+    #[test]
+    #[should_panic(expected = "Account name is reserved")]
+    fn test_reserved_pattern_rejects_matching_name() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // The owner reserves names starting with "admin"
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+        contract.add_reserved_pattern("admin*".to_string());
+
+        // Attempt to create a matching name
+        contract.create_account("admin-team.linkdrop".parse().unwrap(), pk);
+    }
+
+    #[test]
+    #[should_panic(expected = "Account name is reserved")]
+    fn test_ensure_account_and_claim_rejects_reserved_name() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let new_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // The owner reserves names starting with "admin" and creates the linkdrop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.add_reserved_pattern("admin*".to_string());
+        contract.send(pk.clone());
+
+        // Claim with the linkdrop key
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Attempt to claim into a matching name
+        contract.ensure_account_and_claim("admin-team.linkdrop".parse().unwrap(), new_pk, None);
+    }
+
+    #[test]
+    fn test_ensure_account_and_claim_transfers_to_account_it_cannot_create() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let new_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+
+        // Create the linkdrop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+
+        // Claim with the linkdrop key
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Claim into an account outside of this contract, which can only exist already
+        let account_id: AccountId = "alice.near".parse().unwrap();
+        contract.ensure_account_and_claim(account_id.clone(), new_pk, None);
+
+        // The drop is only transferred, no creation is attempted
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == account_id).unwrap();
+        assert_eq!(receipt.actions, vec![VmAction::Transfer { deposit: deposit - ACCESS_KEY_ALLOWANCE }]);
+    }
+
+    fn options_with_contract(bytes: Vec<u8>) -> CreateAccountOptions {
+        CreateAccountOptions {
+            full_access_keys: None,
+            limited_access_keys: None,
+            contract_bytes: Some(bytes),
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        }
+    }
+
+    #[test]
+    fn test_create_advanced_account_contract_under_limit() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+
+        // A contract exactly at the limit is accepted
+        contract.set_max_contract_bytes(U64(16));
+        contract.create_account_advanced(sub_account(), options_with_contract(vec![1; 16]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract too large")]
+    fn test_create_advanced_account_contract_over_limit() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+
+        // One byte over the limit is rejected before anything is deployed
+        contract.set_max_contract_bytes(U64(16));
+        contract.create_account_advanced(sub_account(), options_with_contract(vec![1; 17]));
+    }
+
+    #[test]
+    fn test_owner_can_raise_contract_limit() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        assert_eq!(contract.max_contract_bytes, DEFAULT_MAX_CONTRACT_BYTES);
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+
+        // A contract over the default limit is flagged by the preview
+        let options = || options_with_contract(vec![1; DEFAULT_MAX_CONTRACT_BYTES as usize + 1]);
+        let deposit = U128(ACCESS_KEY_ALLOWANCE * 100);
+        assert_eq!(
+            contract.preview_create_account_advanced(sub_account(), options(), deposit),
+            vec![PreviewWarning::InvalidOptions("Contract too large".to_string())]
+        );
+
+        // Once the owner raises the limit it is accepted
+        contract.set_max_contract_bytes(U64(DEFAULT_MAX_CONTRACT_BYTES * 2));
+        assert!(contract.preview_create_account_advanced(sub_account(), options(), deposit).is_empty());
+    }
+
+    fn empty_options() -> CreateAccountOptions {
+        CreateAccountOptions {
+            full_access_keys: None,
+            limited_access_keys: None,
+            contract_bytes: None,
+            contract_bytes_base64: None,
+            callback_gas: None,
+            limited_access_keys_v2: None,
+            stake: None,
+            key_labels: None
+        }
+    }
+
+    #[test]
+    fn test_validate_options_accepts_valid_options() {
+        let contract = LinkDrop::new(linkdrop(), None);
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        let options = CreateAccountOptions { full_access_keys: Some(vec![pk]), ..empty_options() };
+        assert_eq!(contract.validate_options(options), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_options_duplicate_keys() {
+        let contract = LinkDrop::new(linkdrop(), None);
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // The same key as a full access key and a limited access key
+        let options = CreateAccountOptions {
+            full_access_keys: Some(vec![pk.clone()]),
+            limited_access_keys: Some(vec![LimitedAccessKey {
+                public_key: pk,
+                allowance: U128(100),
+                receiver_id: linkdrop(),
+                method_names: "send".to_string(),
+            }]),
+            ..empty_options()
+        };
+        assert_eq!(contract.validate_options(options), Err("Duplicate public key across access keys".to_string()));
+    }
+
+    #[test]
+    fn test_validate_options_empty_method_name() {
+        let contract = LinkDrop::new(linkdrop(), None);
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        let options = CreateAccountOptions {
+            limited_access_keys_v2: Some(vec![LimitedAccessKeyV2 {
+                public_key: pk,
+                allowance: U128(100),
+                receiver_id: linkdrop(),
+                method_names: vec!["".to_string()],
+            }]),
+            ..empty_options()
+        };
+        assert_eq!(contract.validate_options(options), Err("Method names cannot be empty".to_string()));
+    }
+
+    #[test]
+    fn test_validate_options_empty_contract_bytes() {
+        let contract = LinkDrop::new(linkdrop(), None);
+
+        let options = CreateAccountOptions { contract_bytes: Some(vec![]), ..empty_options() };
+        assert_eq!(contract.validate_options(options), Err("Contract bytes cannot be empty".to_string()));
+    }
+
+    #[test]
+    fn test_validate_options_too_much_callback_gas() {
+        let contract = LinkDrop::new(linkdrop(), None);
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        let options = CreateAccountOptions {
+            full_access_keys: Some(vec![pk]),
+            callback_gas: Some(Gas(MAX_CALLBACK_GAS.0 + 1)),
+            ..empty_options()
+        };
+        assert_eq!(
+            contract.validate_options(options),
+            Err("Callback gas must be between MIN_CALLBACK_GAS and MAX_CALLBACK_GAS".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_options_no_options() {
+        let contract = LinkDrop::new(linkdrop(), None);
+
+        assert_eq!(contract.validate_options(empty_options()), Err(LinkdropError::NoCreateOptions.as_ref().to_string()));
+    }
+
+    #[test]
+    fn test_validate_options_conflicting_contract_bytes() {
+        let contract = LinkDrop::new(linkdrop(), None);
+
+        let options = CreateAccountOptions {
+            contract_bytes: Some(vec![1; 16]),
+            contract_bytes_base64: Some(vec![1; 16].into()),
+            ..empty_options()
+        };
+        assert_eq!(
+            contract.validate_options(options),
+            Err(LinkdropError::ConflictingContractBytes.as_ref().to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_options_contract_over_limit() {
+        let mut contract = LinkDrop::new(linkdrop(), None);
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.set_max_contract_bytes(U64(16));
+
+        assert_eq!(contract.validate_options(options_with_contract(vec![1; 17])), Err("Contract too large".to_string()));
+    }
+
+    #[test]
+    fn test_claim_random_drop_in_range() {
+        let (min, max) = (ACCESS_KEY_ALLOWANCE, ACCESS_KEY_ALLOWANCE * 3);
+        let pool = ACCESS_KEY_ALLOWANCE * 10;
+        let (mut contract, pk) = send_from_bob(pool + ACCESS_KEY_ALLOWANCE, |contract, pk| {
+            contract.send_random(pk, U128(min), U128(max), 3)
+        });
+        assert_eq!(contract.get_key_balance(pk.clone()).unwrap().0, pool);
+        set_claim_context(&pk, pool + ACCESS_KEY_ALLOWANCE);
+
+        // Claim once
+        let amount = contract.claim_with_receipt(sub_account(), None, None).0;
+
+        // The amount is within the range and is taken out of the pool, which keeps its key
+        assert!(min <= amount && amount <= max);
+        assert_eq!(contract.get_key_balance(pk).unwrap().0, pool - amount);
+        assert_eq!(contract.get_total_locked().0, pool - amount);
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == sub_account()).unwrap();
+        assert_eq!(receipt.actions, vec![VmAction::Transfer { deposit: amount }]);
+        assert!(receipts
+            .iter()
+            .flat_map(|receipt| receipt.actions.iter())
+            .all(|action| !matches!(action, VmAction::DeleteKey { .. })));
+    }
+
+    #[test]
+    fn test_claim_random_drop_clamped_to_pool() {
+        // The pool only covers one full claim
+        let amount = ACCESS_KEY_ALLOWANCE * 3;
+        let pool = ACCESS_KEY_ALLOWANCE * 5;
+        let (mut contract, pk) = send_from_bob(pool + ACCESS_KEY_ALLOWANCE, |contract, pk| {
+            contract.send_random(pk, U128(amount), U128(amount), 2)
+        });
+        set_claim_context(&pk, pool + ACCESS_KEY_ALLOWANCE);
+
+        // The first claim pays the full amount, the second one only what is left
+        assert_eq!(contract.claim_with_receipt(sub_account(), None, None).0, amount);
+        assert_eq!(contract.claim_with_receipt(bob(), None, None).0, pool - amount);
+
+        // The pool is used up and its key is deleted
+        assert!(contract.accounts.get(&pk).is_none());
+        assert_eq!(contract.get_total_locked().0, 0);
+        assert!(get_created_receipts()
+            .iter()
+            .flat_map(|receipt| receipt.actions.iter())
+            .any(|action| *action == VmAction::DeleteKey { public_key: pk.clone() }));
+    }
+
+    #[test]
+    fn test_claim_random_drop_last_use_refunds_pool() {
+        let amount = ACCESS_KEY_ALLOWANCE;
+        let pool = ACCESS_KEY_ALLOWANCE * 10;
+        let (mut contract, pk) = send_from_bob(pool + ACCESS_KEY_ALLOWANCE, |contract, pk| {
+            contract.send_random(pk, U128(amount), U128(amount), 1)
+        });
+        set_claim_context(&pk, pool + ACCESS_KEY_ALLOWANCE);
+
+        // The only use pays one amount
+        contract.claim(sub_account(), None, None);
+
+        // The key is deleted and the rest of the pool goes back to bob
+        assert!(contract.accounts.get(&pk).is_none());
+        assert_eq!(contract.get_total_locked().0, 0);
+        assert!(get_created_receipts()
+            .iter()
+            .filter(|receipt| receipt.receiver_id == bob())
+            .any(|receipt| receipt.actions == vec![VmAction::Transfer { deposit: pool - amount }]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Claim cooldown active")]
+    fn test_claim_random_drop_within_cooldown() {
+        let pool = ACCESS_KEY_ALLOWANCE * 10;
+        let (mut contract, pk) = send_from_bob(pool + ACCESS_KEY_ALLOWANCE, |contract, pk| {
+            contract.send_random(pk, U128(ACCESS_KEY_ALLOWANCE), U128(ACCESS_KEY_ALLOWANCE), 3)
+        });
+        // One minute between claims
+        let cooldown = 60_000_000_000;
+
+        // The owner sets the cooldown
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .block_timestamp(cooldown)
+            .context.clone()
+        );
+        contract.set_claim_cooldown(U64(cooldown));
+
+        // Claim once
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk.clone())
+            .account_balance(pool + ACCESS_KEY_ALLOWANCE)
+            .block_timestamp(cooldown)
+            .context.clone()
+        );
+        contract.claim(sub_account(), None, None);
+
+        // Claiming again before the cooldown has passed is rejected
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(pool + ACCESS_KEY_ALLOWANCE)
+            .block_timestamp(cooldown * 2 - 1)
+            .context.clone()
+        );
+        contract.claim(bob(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Random drops can only be claimed with claim")]
+    fn test_claim_amount_rejects_random_drop() {
+        let (mut contract, pk) = send_from_bob(ACCESS_KEY_ALLOWANCE * 11, |contract, pk| {
+            contract.send_random(pk, U128(ACCESS_KEY_ALLOWANCE), U128(ACCESS_KEY_ALLOWANCE), 2)
+        });
+        set_claim_context(&pk, ACCESS_KEY_ALLOWANCE * 11);
+
+        // A chosen amount would bypass the random pick
+        contract.claim_amount(sub_account(), U128(ACCESS_KEY_ALLOWANCE * 10), None);
+    }
+
+    fn implicit_account() -> AccountId {
+        "98793cd91a3f870fb126f66285808c7e094afcfc4eda8a970f6648cdf0dbd6de".parse().unwrap()
+    }
+
+    #[test]
+    #[should_panic(expected = "Use claim for implicit accounts")]
+    fn test_create_account_and_claim_rejects_implicit_account() {
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        let (mut contract, pk) = send_from_bob(deposit, LinkDrop::send);
+        set_claim_context(&pk, deposit);
+        let new_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+
+        // Implicit accounts cannot be created with CreateAccount
+        contract.create_account_and_claim(implicit_account(), new_pk, None, None).unwrap();
+    }
+
+    #[test]
+    fn test_smart_claim_implicit_account_transfers() {
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        let (mut contract, pk) = send_from_bob(deposit, LinkDrop::send);
+        set_claim_context(&pk, deposit);
+        let new_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+
+        // The claimer asks for the account to be created
+        contract.smart_claim(implicit_account(), false, Some(new_pk), None);
+
+        // The drop is transferred straight to the implicit account, like with claim
+        assert!(contract.accounts.get(&pk).is_none());
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == implicit_account()).unwrap();
+        assert_eq!(receipt.actions, vec![VmAction::Transfer { deposit: deposit - ACCESS_KEY_ALLOWANCE }]);
+    }
+
+    #[test]
+    fn test_smart_claim_named_account_creates_it() {
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        let (mut contract, pk) = send_from_bob(deposit, LinkDrop::send);
+        set_claim_context(&pk, deposit);
+        let new_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+
+        // The claimer asks for the account to be created
+        contract.smart_claim(sub_account(), false, Some(new_pk.clone()), None);
+
+        // A named account goes through the usual creation
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == sub_account()).unwrap();
+        assert_eq!(receipt.actions, vec![
+            VmAction::CreateAccount,
+            VmAction::AddKeyWithFullAccess { public_key: new_pk, nonce: 0 },
+            VmAction::Transfer { deposit: deposit - ACCESS_KEY_ALLOWANCE },
+        ]);
     }
 
     #[test]
-    fn test_create_account() {
+    fn test_create_account_free() {
         // Create a new instance of the linkdrop contract
-        let mut contract = LinkDrop::new();
+        let mut contract = LinkDrop::new(linkdrop(), None);
         // Create the public key to be used in the test
         let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
             .parse()
             .unwrap();
-        // Default the deposit to an extremely small amount
-        let deposit = 1_000_000;
 
-        // Initialize the mocked blockchain
+        // The owner creates an account without attaching anything
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
-            .attached_deposit(deposit)
+            .predecessor_account_id(linkdrop())
+            .account_balance(ACCESS_KEY_ALLOWANCE * 100)
             .context.clone()
         );
+        contract.create_account_free(sub_account(), pk.clone());
 
-        // Create bob's account with the PK
-        contract.create_account(bob(), pk);
+        // The contract funds the storage of the new account
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == sub_account()).unwrap();
+        assert_eq!(receipt.actions, vec![
+            VmAction::CreateAccount,
+            VmAction::AddKeyWithFullAccess { public_key: pk, nonce: 0 },
+            VmAction::Transfer { deposit: free_account_amount() },
+        ]);
     }
 
     #[test]
-    #[should_panic]
-    fn test_create_invalid_account() {
+    #[should_panic(expected = "Not enough free balance to fund the account")]
+    fn test_create_account_free_insufficient_balance() {
         // Create a new instance of the linkdrop contract
-        let mut contract = LinkDrop::new();
+        let mut contract = LinkDrop::new(linkdrop(), None);
         // Create the public key to be used in the test
         let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
             .parse()
             .unwrap();
-        // Default the deposit to an extremely small amount
-        let deposit = 1_000_000;
+
+        // The contract holds less than the new account needs
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .account_balance(free_account_amount() - 1)
+            .context.clone()
+        );
+        contract.create_account_free(sub_account(), pk);
+    }
+
+    /// Number of actions of the receipts sent to `receiver_id` that match `predicate`.
+    fn count_actions(receiver_id: &AccountId, predicate: impl Fn(&VmAction) -> bool) -> usize {
+        get_created_receipts()
+            .iter()
+            .filter(|receipt| &receipt.receiver_id == receiver_id)
+            .flat_map(|receipt| receipt.actions.iter())
+            .filter(|action| predicate(action))
+            .count()
+    }
+
+    #[test]
+    fn test_create_account_multikey() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pks: Vec<PublicKey> = vec![
+            "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz".parse().unwrap(),
+            "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca".parse().unwrap(),
+            "4BTYRHcT1s5WCBFdY3gn5rxsdB5tAQkTrUVLhrhDU9Vb".parse().unwrap(),
+        ];
 
         // Initialize the mocked blockchain
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
-            .attached_deposit(deposit)
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE)
             .context.clone()
         );
 
-        // Attempt to create an invalid account with the PK
-        contract.create_account("XYZ".parse().unwrap(), pk);
+        // Create bob's account with three keys
+        contract.create_account_multikey(sub_account(), pks);
+
+        // Each key is added as a full access key and the deposit is transferred once
+        assert_eq!(count_actions(&sub_account(), |action| matches!(action, VmAction::AddKeyWithFullAccess { .. })), 3);
+        assert_eq!(count_actions(&sub_account(), |action| matches!(action, VmAction::Transfer { .. })), 1);
     }
 
     #[test]
-    #[should_panic]
-    fn test_get_missing_balance_panics() {
+    #[should_panic(expected = "At least one public key is required")]
+    fn test_create_account_multikey_without_keys() {
         // Create a new instance of the linkdrop contract
-        let contract = LinkDrop::new();
-        // Create the public key to be used in the test
-        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
-            .parse()
-            .unwrap();
+        let mut contract = LinkDrop::new(linkdrop(), None);
 
         // Initialize the mocked blockchain
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE)
             .context.clone()
         );
 
-        contract.get_key_balance(pk);
+        // An account without keys could never be used
+        contract.create_account_multikey(sub_account(), vec![]);
     }
 
     #[test]
-    fn test_get_missing_balance_success() {
+    #[should_panic(expected = "Claim already in progress")]
+    fn test_claim_while_create_account_and_claim_in_flight() {
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        let (mut contract, pk) = send_from_bob(deposit, LinkDrop::send);
+        let new_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+
+        // The drop is claimed into a new account, without resolving the callback
+        set_claim_context(&pk, deposit);
+        contract.create_account_and_claim(sub_account(), new_pk, None, None).unwrap();
+        assert!(contract.claiming.contains(&pk));
+
+        // The same transaction submitted again as a plain claim
+        contract.claim(bob(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Claim already in progress")]
+    fn test_create_account_and_claim_twice_in_flight() {
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        let (mut contract, pk) = send_from_bob(deposit, LinkDrop::send);
+        let new_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+
+        // The drop is claimed into a new account, without resolving the callback
+        set_claim_context(&pk, deposit);
+        contract.create_account_and_claim(sub_account(), new_pk.clone(), None, None).unwrap();
+        assert!(contract.claiming.contains(&pk));
+
+        // The same transaction submitted again before the callback ran
+        let _ = contract.create_account_and_claim(sub_account(), new_pk, None, None);
+    }
+
+    #[test]
+    fn test_get_net_claim_amount() {
         // Create a new instance of the linkdrop contract
-        let mut contract = LinkDrop::new();
-        // Create the public key to be used in the test
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
         let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
             .parse()
             .unwrap();
-        // Default the deposit to be 100 times the access key allowance
+        let random_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
         let deposit = ACCESS_KEY_ALLOWANCE * 100;
-        
-        // Initialize the mocked blockchain
+
+        // The owner takes a 1% fee on sends
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
             .attached_deposit(deposit)
             .context.clone()
         );
+        contract.set_fee_basis_points(100);
 
-        // Create the linkdrop
+        // A plain drop pays its balance, which already excludes the fee and the allowance
         contract.send(pk.clone());
+        let balance = deposit - ACCESS_KEY_ALLOWANCE - deposit / 100;
+        assert_eq!(contract.get_key_balance(pk.clone()).unwrap().0, balance);
+        assert_eq!(contract.get_net_claim_amount(pk).unwrap().0, balance);
 
-        // try getting the balance of the key
-        let balance:u128 = contract.get_key_balance(pk).0;
-        assert_eq!(
-            balance,
-            deposit - ACCESS_KEY_ALLOWANCE
-        );
+        // A random drop pays at most its maximum per claim
+        contract.send_random(random_pk.clone(), U128(1), U128(ACCESS_KEY_ALLOWANCE), 10);
+        assert_eq!(contract.get_key_balance(random_pk.clone()).unwrap().0, balance);
+        assert_eq!(contract.get_net_claim_amount(random_pk).unwrap().0, ACCESS_KEY_ALLOWANCE);
     }
 
     #[test]
-    #[should_panic]
-    fn test_claim_invalid_account() {
+    fn test_refresh_allowance() {
         // Create a new instance of the linkdrop contract
-        let mut contract = LinkDrop::new();
+        let mut contract = LinkDrop::new(linkdrop(), None);
         // Create the public key to be used in the test
         let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
             .parse()
             .unwrap();
-        // Default the deposit to be 100 times the access key allowance
         let deposit = ACCESS_KEY_ALLOWANCE * 100;
-        
-        // Initialize the mocked blockchain
+
+        // Create a drop meant to be withdrawn from many times
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
             .attached_deposit(deposit)
             .context.clone()
         );
-
-        // Create the linkdrop
         contract.send(pk.clone());
 
-        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        // Its key burned the whole allowance on withdrawals; the owner refreshes it from the contract's
+        // own balance, which is what is left above the drop and its reserved allowance
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
             .predecessor_account_id(linkdrop())
-            .signer_account_pk(pk.into())
-            .account_balance(deposit)
+            .account_balance(deposit * 2)
             .context.clone()
         );
+        contract.refresh_allowance(pk.clone());
 
-        // Create the second public key
-        let pk2 = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
-            .parse()
-            .unwrap();
-        // Attempt to create the account and claim
-        contract.create_account_and_claim("XYZ".parse().unwrap(), pk2);
+        // The key is replaced by itself with a fresh allowance and keeps its methods
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].actions, vec![
+            VmAction::DeleteKey { public_key: pk.clone() },
+            VmAction::AddKeyWithFunctionCall {
+                public_key: pk.clone(),
+                nonce: 0,
+                allowance: Some(ACCESS_KEY_ALLOWANCE),
+                receiver_id: linkdrop(),
+                function_names: ACCESS_KEY_METHOD_NAMES.split(',').map(String::from).collect(),
+            },
+        ]);
+        assert_eq!(contract.get_key_balance(pk).unwrap().0, deposit - ACCESS_KEY_ALLOWANCE);
+        assert_eq!(contract.locked_allowances, ACCESS_KEY_ALLOWANCE);
     }
 
     #[test]
-    fn test_drop_claim() {
+    #[should_panic(expected = "Not enough free balance to refresh the allowance")]
+    fn test_refresh_allowance_without_free_balance() {
         // Create a new instance of the linkdrop contract
-        let mut contract = LinkDrop::new();
+        let mut contract = LinkDrop::new(linkdrop(), None);
         // Create the public key to be used in the test
         let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
             .parse()
             .unwrap();
-        // Default the deposit to be 100 times the access key allowance
         let deposit = ACCESS_KEY_ALLOWANCE * 100;
-        
-        // Initialize the mocked blockchain
+
+        // Create the drop
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
             .attached_deposit(deposit)
             .context.clone()
         );
-
-        // Create the linkdrop
         contract.send(pk.clone());
 
-        // Now, send new transaction to linkdrop contract and reinitialize the mocked blockchain with new params
+        // The contract only holds the drop and its reserved allowance
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
             .predecessor_account_id(linkdrop())
-            .signer_account_pk(pk.into())
             .account_balance(deposit)
             .context.clone()
         );
+        contract.refresh_allowance(pk);
+    }
 
-        // Create the second public key
-        let pk2 = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+    #[test]
+    fn test_top_up_without_deposit() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
             .parse()
             .unwrap();
-        // Attempt to create the account and claim
-        contract.create_account_and_claim(bob(), pk2);
+
+        // Send a drop to the key
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+
+        // Topping it up with nothing attached is rejected and leaves the drop as it was
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .context.clone()
+        );
+        assert_eq!(contract.top_up(pk.clone()), Err(LinkdropError::InsufficientDeposit));
+        assert_eq!(contract.get_key_balance(pk).unwrap().0, ACCESS_KEY_ALLOWANCE);
     }
 
     #[test]
-    fn test_send_two_times() {
+    fn test_views_of_missing_key() {
         // Create a new instance of the linkdrop contract
-        let mut contract = LinkDrop::new();
+        let contract = LinkDrop::new(linkdrop(), None);
         // Create the public key to be used in the test
         let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
             .parse()
             .unwrap();
-        // Default the deposit to be 100 times the access key allowance
-        let deposit = ACCESS_KEY_ALLOWANCE * 100;
-        
+
         // Initialize the mocked blockchain
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
-            .attached_deposit(deposit)
             .context.clone()
         );
 
-        // Create the linkdrop
-        contract.send(pk.clone());
-        assert_eq!(contract.get_key_balance(pk.clone()), (deposit - ACCESS_KEY_ALLOWANCE).into());
+        // Every view of a key without a drop fails with the same error
+        assert_eq!(contract.get_net_claim_amount(pk.clone()), Err(LinkdropError::KeyMissing));
+        assert_eq!(contract.get_key_information(pk).err(), Some(LinkdropError::KeyMissing));
+    }
 
-        // Re-initialize the mocked blockchain with new params
+    #[test]
+    fn test_only_owner_can_remove_admin() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+
+        // The owner makes bob an admin
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
-            .account_balance(deposit)
-            .attached_deposit(deposit + 1)
+            .predecessor_account_id(linkdrop())
             .context.clone()
         );
+        assert_eq!(contract.add_admin(bob()), Ok(true));
 
-        // Attempt to recreate the same linkdrop twice
-        contract.send(pk.clone());
-        assert_eq!(
-            contract.accounts.get(&pk.into()).unwrap(),
-            deposit + deposit + 1 - 2 * ACCESS_KEY_ALLOWANCE
+        // Bob cannot revoke admin rights, not even their own
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
         );
+        assert_eq!(contract.remove_admin(bob()), Err(LinkdropError::Unauthorized));
+        assert_eq!(contract.get_admins(), vec![bob()]);
     }
 
     #[test]
-    fn test_create_advanced_account() {
+    fn test_send_with_methods() {
+        // Create a new instance of the linkdrop contract
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let claim_pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let call_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+
+        // One drop restricted to claim, another that can also be claimed with claim_and_call
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send_with_methods(claim_pk.clone(), "claim".to_string());
+        contract.send_with_methods(call_pk.clone(), "claim,claim_and_call".to_string());
+
+        // Each access key gets the methods of its own drop
+        let claim_methods = vec!["claim".to_string()];
+        let call_methods = vec!["claim".to_string(), "claim_and_call".to_string()];
+        assert_eq!(contract.get_key_methods(claim_pk.clone()), Some(claim_methods.clone()));
+        assert_eq!(contract.get_key_methods(call_pk.clone()), Some(call_methods.clone()));
+        let actions: Vec<VmAction> = get_created_receipts().into_iter().flat_map(|receipt| receipt.actions).collect();
+        assert!(actions.contains(&VmAction::AddKeyWithFunctionCall {
+            public_key: claim_pk,
+            nonce: 0,
+            allowance: Some(ACCESS_KEY_ALLOWANCE),
+            receiver_id: linkdrop(),
+            function_names: claim_methods,
+        }));
+        assert!(actions.contains(&VmAction::AddKeyWithFunctionCall {
+            public_key: call_pk,
+            nonce: 0,
+            allowance: Some(ACCESS_KEY_ALLOWANCE),
+            receiver_id: linkdrop(),
+            function_names: call_methods,
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Method names cannot be empty")]
+    fn test_send_with_empty_methods() {
         // Create a new instance of the linkdrop contract
-        let mut contract = LinkDrop::new();
+        let mut contract = LinkDrop::new(linkdrop(), None);
         // Create the public key to be used in the test
         let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
             .parse()
             .unwrap();
-        // Default the deposit to an extremely small amount
-        let deposit = 1_000_000;
-
-        // Create options for the advanced account creation
-        let options: CreateAccountOptions = CreateAccountOptions {
-            full_access_keys: Some(vec![pk.clone()]),
-            limited_access_keys: Some(vec![LimitedAccessKey {
-                public_key: pk.clone(),
-                allowance: U128(100),
-                receiver_id: linkdrop(),
-                method_names: "send".to_string(),
-            }]),
-            contract_bytes: Some(include_bytes!("../target/wasm32-unknown-unknown/release/linkdrop.wasm").to_vec()),
-            contract_bytes_base64: None
-        };
 
         // Initialize the mocked blockchain
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
-            .attached_deposit(deposit)
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
             .context.clone()
         );
+        contract.send_with_methods(pk, "".to_string());
+    }
 
-        // Create bob's account with the advanced options
-        contract.create_account_advanced(bob(), options);
+    #[test]
+    #[should_panic(expected = "Drop keys can only call claim methods")]
+    fn test_send_with_owner_methods() {
+        // A drop key allowed to call owner methods could sweep the contract as soon as an owner check slips
+        send_from_bob(ACCESS_KEY_ALLOWANCE * 2, |contract, pk| {
+            contract.send_with_methods(pk, "claim,sweep_unallocated,add_admin".to_string())
+        });
     }
 
     #[test]
-    fn test_create_advanced_account_with_base64_contract_byte_string() {
+    fn test_get_stats() {
         // Create a new instance of the linkdrop contract
-        let mut contract = LinkDrop::new();
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        let pk3: PublicKey = "4BTYRHcT1s5WCBFdY3gn5rxsdB5tAQkTrUVLhrhDU9Vb"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
 
-        // Default the deposit to an extremely small amount
-        let deposit = 1_000_000;
+        // Bob funds three linkdrops
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+        contract.send(pk2.clone());
+        contract.send(pk3);
 
-        // Create options for the advanced account creation
-        let options: CreateAccountOptions = CreateAccountOptions {
-            full_access_keys: None,
-            limited_access_keys: None,
-            contract_bytes: None,
-            contract_bytes_base64: Some(include_bytes!("../target/wasm32-unknown-unknown/release/linkdrop.wasm").to_vec().into()),
-        };
+        // The first one is claimed
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit * 3)
+            .context.clone()
+        );
+        contract.claim(bob(), None, None);
 
-        // Initialize the mocked blockchain
+        // Bob reclaims the second one
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
-            .attached_deposit(deposit)
+            .predecessor_account_id(bob())
+            .account_balance(deposit * 3)
             .context.clone()
         );
+        contract.cancel_many(vec![pk2]);
 
-        // Create bob's account with the advanced options
-        contract.create_account_advanced(bob(), options);
+        // The stats match the individual counters
+        let stats = contract.get_stats();
+        assert_eq!(stats.total_drops, 1);
+        assert_eq!(stats.total_locked, contract.get_total_locked());
+        assert_eq!(stats.total_claimed, contract.get_monitoring_snapshot().claimed_count);
+        assert_eq!(stats.total_reclaimed, contract.get_funder_stats(bob()).unwrap().reclaimed);
+        assert_eq!(stats.total_reclaimed, 1);
+        assert!(!stats.paused);
     }
 
-    #[test]
-    #[should_panic]
-    fn test_create_advanced_account_no_options() {
-        // Create a new instance of the linkdrop contract
-        let mut contract = LinkDrop::new();
-        // Default the deposit to an extremely small amount
-        let deposit = 1_000_000;
+    /// Sends a drop of bob, lets the owner fund the sponsorship reserve with `reserve` and sponsor
+    /// new accounts with `amount`, then claims the drop into a new account.
+    /// Returns the contract and the balance of the drop.
+    fn create_account_and_claim_sponsored(reserve: Balance, amount: Balance) -> (LinkDrop, Balance) {
+        let deposit = ACCESS_KEY_ALLOWANCE * 100;
+        let (mut contract, pk) = send_from_bob(deposit, LinkDrop::send);
 
-        // Initialize the mocked blockchain
+        // The owner sets up sponsorship
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
-            .attached_deposit(deposit)
+            .predecessor_account_id(linkdrop())
+            .attached_deposit(reserve)
             .context.clone()
         );
+        if reserve > 0 {
+            contract.fund_sponsorship_reserve();
+        }
+        contract.set_sponsorship_amount(U128(amount));
 
-        // Create bob's account with the advanced options
-        contract.create_account_advanced(bob(), CreateAccountOptions { full_access_keys: None, limited_access_keys: None, contract_bytes: None, contract_bytes_base64: None });
+        // The drop is claimed into a new account
+        set_claim_context(&pk, deposit + reserve);
+        let new_pk: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        contract.create_account_and_claim(sub_account(), new_pk, None, None).unwrap();
+        (contract, deposit - ACCESS_KEY_ALLOWANCE)
     }
 
     #[test]
-    #[should_panic]
-    fn test_create_advanced_account_conflict_contract_bytes() {
-        // Create a new instance of the linkdrop contract
-        let mut contract = LinkDrop::new();
-        // Default the deposit to an extremely small amount
-        let deposit = 1_000_000;
+    fn test_create_account_and_claim_sponsored() {
+        let amount = ACCESS_KEY_ALLOWANCE / 100;
+        let (contract, balance) = create_account_and_claim_sponsored(amount * 3, amount);
 
-        // Initialize the mocked blockchain
+        // The new account gets the drop and the sponsored amount, which is taken from the reserve
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == sub_account()).unwrap();
+        assert!(receipt.actions.contains(&VmAction::Transfer { deposit: balance + amount }));
+        assert_eq!(contract.get_sponsorship_reserve().0, amount * 2);
+    }
+
+    #[test]
+    fn test_create_account_and_claim_with_empty_sponsorship_reserve() {
+        let amount = ACCESS_KEY_ALLOWANCE / 100;
+        let (contract, balance) = create_account_and_claim_sponsored(0, amount);
+
+        // Without a reserve the claim still goes through, with the drop alone
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == sub_account()).unwrap();
+        assert!(receipt.actions.contains(&VmAction::Transfer { deposit: balance }));
+        assert_eq!(contract.get_sponsorship_reserve().0, 0);
+    }
+
+    #[test]
+    fn test_transfer_drop_ownership() {
+        // Bob creates the linkdrop and hands it over
+        let (mut contract, pk) = send_from_bob(ACCESS_KEY_ALLOWANCE * 2, LinkDrop::send);
+        contract.transfer_drop_ownership(pk.clone(), sub_account());
+
+        // The drop is now listed under the new funder only
+        assert_eq!(contract.get_key_information(pk.clone()).unwrap().funder_id, Some(sub_account()));
+        assert!(contract.get_drops_by_funder(bob(), 0, 10).is_empty());
+        assert_eq!(contract.get_drops_by_funder(sub_account(), 0, 10), vec![(pk.clone(), U128(ACCESS_KEY_ALLOWANCE))]);
+
+        // The new funder can reclaim it
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(linkdrop())
-            .attached_deposit(deposit)
+            .predecessor_account_id(sub_account())
+            .account_balance(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        assert_eq!(contract.cancel_many(vec![pk.clone()]), 1);
+        assert!(!contract.is_claimable(pk));
+    }
+
+    #[test]
+    fn test_old_funder_cannot_reclaim_transferred_drop() {
+        // Bob creates the linkdrop and hands it over
+        let (mut contract, pk) = send_from_bob(ACCESS_KEY_ALLOWANCE * 2, LinkDrop::send);
+        contract.transfer_drop_ownership(pk.clone(), sub_account());
+
+        // Bob tries to take the drop back after selling it
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .account_balance(ACCESS_KEY_ALLOWANCE * 2)
             .context.clone()
         );
+        assert_eq!(contract.reclaim_batch(vec![pk.clone()]), vec![pk.clone()]);
+        assert!(contract.is_claimable(pk));
+    }
 
-        // Create bob's account with the advanced options
-        contract.create_account_advanced(bob(), CreateAccountOptions {
-            full_access_keys: None,
-            limited_access_keys: None,
-            contract_bytes: Some(include_bytes!("../target/wasm32-unknown-unknown/release/linkdrop.wasm").to_vec()),
-            contract_bytes_base64: Some(include_bytes!("../target/wasm32-unknown-unknown/release/linkdrop.wasm").to_vec().into())
-        });
+    #[test]
+    #[should_panic(expected = "Only the funder of the drop can transfer it")]
+    fn test_transfer_drop_ownership_not_funder() {
+        // Bob creates the linkdrop and hands it over
+        let (mut contract, pk) = send_from_bob(ACCESS_KEY_ALLOWANCE * 2, LinkDrop::send);
+        contract.transfer_drop_ownership(pk.clone(), sub_account());
+
+        // Bob is no longer the funder, so cannot transfer it again
+        contract.transfer_drop_ownership(pk, bob());
     }
 }
@@ -0,0 +1,118 @@
+use crate::*;
+
+/// Storage prefix of the collections of a migrated contract. The previous layout keeps its balances
+/// under the default empty prefix until `migrate_drops` has moved all of them.
+const MIGRATED_STORAGE_PREFIX: &[u8] = b"v2";
+
+/// Methods the access keys of drops from the previous layout were added with.
+const LEGACY_ACCESS_KEY_METHOD_NAMES: &str = "claim,create_account_and_claim";
+
+/// State layout of the contract before `total_locked` was tracked and drops stored only a balance.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldLinkDrop {
+    pub accounts: UnorderedMap<PublicKey, Balance>,
+}
+
+#[near_bindgen]
+impl LinkDrop {
+    /// Migrates the contract state from the previous layout. Only the new state is set up here, so the
+    /// call fits in one transaction whatever the number of drops: the old balances stay where they are
+    /// and are moved by `migrate_drops`. The contract stays paused until all of them are moved.
+    /// The previous layout had no owner, so `owner_id` must be given. It cannot be the contract account,
+    /// which drop keys act as when they call the contract.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate(owner_id: AccountId) -> Self {
+        assert_ne!(owner_id, env::current_account_id(), "The contract account cannot be the owner");
+        let old: OldLinkDrop = env::state_read().expect("Failed to read old state");
+        let mut contract = Self::new(owner_id, Some(MIGRATED_STORAGE_PREFIX.to_vec()));
+        contract.paused = !old.accounts.is_empty();
+        contract.legacy_accounts = Some(old.accounts).filter(|accounts| !accounts.is_empty());
+        contract
+    }
+
+    /// Moves up to `limit` balances of the previous layout into drops without a password or creation time,
+    /// adding them to `total_locked`. The drops record the methods their keys were added with, so that
+    /// `refresh_allowance` and `rotate_key` keep them. Once none are left, the contract is unpaused.
+    /// Returns the number of balances left to move.
+    #[private]
+    pub fn migrate_drops(&mut self, limit: u64) -> u64 {
+        let mut legacy_accounts = self.legacy_accounts.take().expect("Nothing to migrate");
+        for _ in 0..limit {
+            let pk = match legacy_accounts.keys_as_vector().get(legacy_accounts.len().saturating_sub(1)) {
+                Some(pk) => pk,
+                None => break,
+            };
+            let balance = legacy_accounts.remove(&pk).unwrap();
+            self.lock(balance);
+            self.lock_allowance(ACCESS_KEY_ALLOWANCE);
+            self.accounts.insert(&pk, &DropInfo {
+                method_names: Some(LEGACY_ACCESS_KEY_METHOD_NAMES.to_string()),
+                ..DropInfo::new(balance)
+            });
+        }
+        let left = legacy_accounts.len();
+        if left == 0 {
+            self.paused = false;
+        } else {
+            self.legacy_accounts = Some(legacy_accounts);
+        }
+        left
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    #[test]
+    fn test_migrate_drops_in_pages() {
+        // Initialize the mocked blockchain with calls from the contract itself
+        let contract_id: AccountId = "linkdrop".parse().unwrap();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(contract_id.clone())
+            .predecessor_account_id(contract_id)
+            .context.clone()
+        );
+
+        // Write the old state with two outstanding drops
+        let mut old = OldLinkDrop {
+            accounts: UnorderedMap::new(b"a"),
+        };
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        old.accounts.insert(&pk, &100);
+        old.accounts.insert(&pk2, &250);
+        env::state_write(&old);
+
+        // Nothing is moved by the migration itself and the contract waits paused
+        let mut contract = LinkDrop::migrate("owner".parse().unwrap());
+        assert!(contract.paused);
+        assert_eq!(contract.owner_id, "owner".parse::<AccountId>().unwrap());
+        assert_eq!(contract.get_total_locked().0, 0);
+
+        // The drops are moved one page at a time
+        assert_eq!(contract.migrate_drops(1), 1);
+        assert!(contract.paused);
+        assert_eq!(contract.migrate_drops(10), 0);
+        assert!(!contract.paused);
+        assert!(contract.legacy_accounts.is_none());
+        assert_eq!(contract.get_total_locked().0, 350);
+        assert_eq!(contract.get_key_balance(pk.clone()).unwrap().0, 100);
+        assert_eq!(contract.get_key_information(pk.clone()).unwrap().created_at, 0);
+        assert_eq!(
+            contract.get_key_methods(pk),
+            Some(vec!["claim".to_string(), "create_account_and_claim".to_string()])
+        );
+        assert_eq!(contract.get_key_balance(pk2).unwrap().0, 250);
+    }
+}
@@ -3,6 +3,204 @@ use near_sdk::serde::{Serialize, Deserialize};
 
 use crate::*;
 
+/// Keys of the contract collections, appended to the contract's storage prefix.
+pub enum StorageKey {
+    Accounts,
+    Admins,
+    FunderStats,
+    KeyLabels,
+    DropsByFunder,
+    FunderDrops,
+    ReservedPatterns,
+    AccountClaims,
+    RetryQueue,
+    StorageBalances,
+    Claiming,
+}
+
+impl StorageKey {
+    /// Returns the storage prefix of the collection under the given base prefix.
+    pub fn with_prefix(&self, base: &[u8]) -> Vec<u8> {
+        let key: &[u8] = match self {
+            StorageKey::Accounts => b"a",
+            StorageKey::Admins => b"m",
+            StorageKey::FunderStats => b"f",
+            StorageKey::KeyLabels => b"l",
+            StorageKey::DropsByFunder => b"d",
+            StorageKey::FunderDrops => b"D",
+            StorageKey::ReservedPatterns => b"r",
+            StorageKey::AccountClaims => b"c",
+            StorageKey::RetryQueue => b"q",
+            StorageKey::StorageBalances => b"s",
+            StorageKey::Claiming => b"i",
+        };
+        [base, key].concat()
+    }
+}
+
+/// A drop registered under a public key.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct DropInfo {
+    /// yoctoNEAR$ amount that will be sent to the claiming account.
+    pub balance: Balance,
+    /// Account that funded the drop. Unknown for drops created before funders were tracked.
+    pub funder_id: Option<AccountId>,
+    /// SHA-256 hash of the password that must be presented at claim time, if any.
+    pub password_hash: Option<CryptoHash>,
+    /// Free-form metadata attached by the funder (e.g. a campaign ID).
+    pub metadata: Option<String>,
+    /// NFT held by the contract that is sent to the claiming account along with the balance.
+    pub nft: Option<NftDrop>,
+    /// Function call keys added to the account created by `create_account_and_claim`, next to its full access key.
+    pub new_account_limited_keys: Option<Vec<LimitedAccessKey>>,
+    /// Pending `commit_claim` commitment. Until it expires, the drop can only be claimed with `reveal_claim`.
+    pub commitment: Option<ClaimCommitment>,
+    /// Block timestamp in nanoseconds at which the drop was created. Zero for drops created before it was tracked.
+    pub created_at: u64,
+    /// yoctoNEAR$ allowance of the drop's access key, zero if unlimited.
+    pub allowance: Balance,
+    /// Message from the funder logged in the claim event for wallets to display, e.g. a greeting.
+    pub claim_message: Option<String>,
+    /// Block timestamp in nanoseconds of the last `claim_amount` withdrawal, zero if there was none.
+    pub last_claim_at: u64,
+    /// Account paid instead when `create_account_and_claim` fails to create the account. Without one, the drop is restored.
+    pub fallback_account: Option<AccountId>,
+    /// Range of the amounts paid by each claim of a drop created with `send_random`.
+    pub random: Option<RandomRange>,
+    /// Comma-separated methods the drop's access key can call, if set with `send_advanced` or
+    /// `set_default_claim_methods`. `None` for keys added with ACCESS_KEY_METHOD_NAMES.
+    pub method_names: Option<String>,
+    /// Account whose storage balance paid for the drop's storage and the amount charged, given back to it
+    /// when the drop is removed. `None` if the contract pays for the storage.
+    pub storage_charge: Option<(AccountId, Balance)>,
+    /// Token registered by the funder with `expect_nft`, the only one `nft_on_transfer` attaches to the drop.
+    pub expected_nft: Option<NftDrop>,
+}
+
+/// Amounts paid by the claims of a random drop, drawn from the drop balance until it runs out.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct RandomRange {
+    /// Smallest yoctoNEAR$ amount a claim pays, unless less is left in the drop.
+    pub min: Balance,
+    /// Largest yoctoNEAR$ amount a claim pays.
+    pub max: Balance,
+    /// Number of claims left before the key is deleted.
+    pub uses_left: u32,
+}
+
+impl RandomRange {
+    /// Picks an amount in `[min, max]` from the block's random seed.
+    pub fn pick(&self) -> Balance {
+        let seed = env::random_seed_array();
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&seed[..16]);
+        self.min + u128::from_le_bytes(bytes) % (self.max - self.min + 1)
+    }
+}
+
+/// A `create_account` call whose account creation failed, kept with its deposit for another attempt.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FailedCreation {
+    /// The account that could not be created.
+    pub new_account_id: AccountId,
+    /// Full access key of the new account.
+    pub public_key: PublicKey,
+    /// yoctoNEAR$ deposit transferred to the new account, refunded once the retries run out.
+    pub amount: U128,
+    /// Caller of `create_account`, who gets the refund.
+    pub predecessor_account_id: AccountId,
+    /// Number of attempts made after the first one.
+    pub retries: u32,
+}
+
+/// What `create_account_advanced` records once the account exists, passed along to `on_account_created`.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AdvancedCreation {
+    /// Summary emitted in the `create_account_advanced` event.
+    pub log: CreateAccountAdvancedLog,
+    /// Labels of keys added to the new account.
+    pub key_labels: Option<Vec<(PublicKey, String)>>,
+}
+
+/// NEP-145 storage balance of an account.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalance {
+    /// yoctoNEAR$ deposited for storage.
+    pub total: U128,
+    /// Part of `total` not used by drops yet, which can be withdrawn.
+    pub available: U128,
+}
+
+/// Hashed claim intent registered with `commit_claim`.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ClaimCommitment {
+    /// SHA-256 hash of the receiving account ID followed by a secret salt.
+    pub hash: CryptoHash,
+    /// Block height until which no other claim of the drop is accepted.
+    pub locked_until: BlockHeight,
+}
+
+/// NFT attached to a drop through `nft_on_transfer`, or expected to be with `expect_nft`.
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct NftDrop {
+    /// The NEP-171 contract of the token.
+    pub contract_id: AccountId,
+    /// The token held by the linkdrop contract.
+    pub token_id: String,
+}
+
+impl DropInfo {
+    /// Creates a drop holding only the given balance.
+    pub fn new(balance: Balance) -> Self {
+        Self {
+            balance,
+            funder_id: None,
+            password_hash: None,
+            metadata: None,
+            nft: None,
+            new_account_limited_keys: None,
+            commitment: None,
+            created_at: 0,
+            allowance: ACCESS_KEY_ALLOWANCE,
+            claim_message: None,
+            last_claim_at: 0,
+            fallback_account: None,
+            random: None,
+            method_names: None,
+            storage_charge: None,
+            expected_nft: None,
+        }
+    }
+
+    /// Whether the drop sets any field that only the funder of an existing drop can change.
+    pub fn has_funder_settings(&self) -> bool {
+        self.password_hash.is_some()
+            || self.metadata.is_some()
+            || self.nft.is_some()
+            || self.new_account_limited_keys.is_some()
+            || self.claim_message.is_some()
+            || self.fallback_account.is_some()
+    }
+
+    /// Comma-separated methods the drop's access key can call.
+    pub fn key_method_names(&self) -> &str {
+        self.method_names.as_deref().unwrap_or(ACCESS_KEY_METHOD_NAMES)
+    }
+
+    /// Gas to attach to a claim of this drop. Covers `create_account_and_claim`, the most expensive
+    /// way to claim, plus the delivery of the NFT if the drop holds one.
+    pub fn estimated_claim_gas(&self) -> Gas {
+        let gas = CREATE_ACCOUNT_AND_CLAIM_GAS + ON_CREATE_ACCOUNT_CALLBACK_GAS;
+        match self.nft {
+            Some(_) => gas + GAS_FOR_NFT_TRANSFER,
+            None => gas,
+        }
+    }
+}
+
 /// Information about a specific public key. Should be returned in the `get_key_information` view method.
 /// Part of the linkdrop NEP
 #[derive(Serialize)]
@@ -11,12 +209,115 @@ pub struct KeyInfo {
     /// yoctoNEAR$ amount that will be sent to the claiming account (either new or existing)
     /// when the key is successfully used.
     pub balance: U128,
+    /// Metadata attached to the drop by the funder, if any.
+    pub metadata: Option<String>,
+    /// Block timestamp in nanoseconds at which the drop was created, or zero if unknown.
+    pub created_at: u64,
+    /// Gas a wallet should attach to claim the drop.
+    pub estimated_claim_gas: Gas,
+    /// Account that funded the drop, if known.
+    pub funder_id: Option<AccountId>,
+    /// Block timestamp in nanoseconds after which the drop can no longer be claimed. Drops do not expire yet,
+    /// so this is always `None`.
+    pub expires_at: Option<u64>,
+    /// Number of claims left for drops that can be claimed several times, i.e. random drops.
+    pub uses_remaining: Option<u32>,
 }
 
+impl From<DropInfo> for KeyInfo {
+    fn from(drop: DropInfo) -> Self {
+        Self {
+            estimated_claim_gas: drop.estimated_claim_gas(),
+            balance: drop.balance.into(),
+            metadata: drop.metadata,
+            created_at: drop.created_at,
+            funder_id: drop.funder_id,
+            expires_at: None,
+            uses_remaining: drop.random.map(|random| random.uses_left),
+        }
+    }
+}
 
-#[derive(Serialize, Deserialize)]
+/// Breakdown of the contract balance returned by `get_balance_breakdown`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BalanceBreakdown {
+    /// Total yoctoNEAR$ held by the contract account.
+    pub total: U128,
+    /// yoctoNEAR$ staked to cover the contract's storage usage.
+    pub storage_staked: U128,
+    /// yoctoNEAR$ owed to outstanding drops.
+    pub locked: U128,
+    /// yoctoNEAR$ not tied to storage or drops, including storage freed by removed drops.
+    pub available: U128,
+}
+
+/// Drop counters of a funder returned by `get_funder_stats`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FunderStats {
+    /// Number of drops created by the funder. Topping up an existing drop does not count.
+    pub created: u64,
+    /// Number of the funder's drops claimed in full.
+    pub claimed: u64,
+    /// Number of the funder's drops taken back by the funder.
+    pub reclaimed: u64,
+}
+
+/// Monitoring snapshot returned by `get_monitoring_snapshot`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Snapshot {
+    /// Number of outstanding drops.
+    pub drops: u64,
+    /// yoctoNEAR$ owed to outstanding drops.
+    pub total_locked: U128,
+    /// yoctoNEAR$ not tied to storage or drops.
+    pub available: U128,
+    /// Number of successful claims since deployment.
+    pub claimed_count: u64,
+    /// yoctoNEAR$ paid out by successful claims since deployment.
+    pub claimed_amount: U128,
+    /// Whether the contract is paused.
+    pub paused: bool,
+    /// Account allowed to manage admins.
+    pub owner_id: AccountId,
+}
+
+/// Contract-wide drop counters returned by `get_stats`.
+#[derive(Serialize)]
 #[serde(crate = "near_sdk::serde")]
-/// Information about any limited access keys that are being added to the account as part of `create_account_advanced`.
+pub struct ContractStats {
+    /// Number of outstanding drops.
+    pub total_drops: u64,
+    /// yoctoNEAR$ owed to outstanding drops.
+    pub total_locked: U128,
+    /// Number of successful claims since deployment, partial withdrawals included.
+    pub total_claimed: u64,
+    /// Number of drops taken back by their funders since deployment.
+    pub total_reclaimed: u64,
+    /// Whether the contract is paused.
+    pub paused: bool,
+}
+
+/// Description of the deployed code returned by `contract_metadata`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractMetadata {
+    /// Version of the crate the contract was built from.
+    pub version: String,
+    /// Standards implemented by the contract.
+    pub standards: Vec<String>,
+    /// yoctoNEAR$ allowance given to the access key of each drop.
+    pub access_key_allowance: U128,
+    /// Comma-separated methods the access key of each new drop can call.
+    pub default_claim_methods: String,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+/// Information about any limited access keys that are being added to the account as part of `create_account_advanced`
+/// or `create_account_and_claim`.
 pub struct LimitedAccessKey {
     /// The public key of the limited access key.
     pub public_key: PublicKey,
@@ -27,6 +328,31 @@ pub struct LimitedAccessKey {
     /// Which methods should this key be allowed to call.
     pub method_names: String,
 }
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+/// Same as `LimitedAccessKey`, but with the method names given as a list instead of a comma-separated string.
+pub struct LimitedAccessKeyV2 {
+    /// The public key of the limited access key.
+    pub public_key: PublicKey,
+    /// The amount of yoctoNEAR$ that can be spent on Gas by this key.
+    pub allowance: U128,
+    /// Which contract should this key be allowed to call.
+    pub receiver_id: AccountId,
+    /// Which methods should this key be allowed to call.
+    pub method_names: Vec<String>,
+}
+
+impl From<LimitedAccessKeyV2> for LimitedAccessKey {
+    fn from(key: LimitedAccessKeyV2) -> Self {
+        Self {
+            public_key: key.public_key,
+            allowance: key.allowance,
+            receiver_id: key.receiver_id,
+            method_names: key.method_names.join(","),
+        }
+    }
+}
     
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
@@ -35,5 +361,43 @@ pub struct CreateAccountOptions {
     pub full_access_keys: Option<Vec<PublicKey>>,
     pub limited_access_keys: Option<Vec<LimitedAccessKey>>,
     pub contract_bytes: Option<Vec<u8>>,
-    pub contract_bytes_base64: Option<Base64VecU8>
+    pub contract_bytes_base64: Option<Base64VecU8>,
+    /// Gas for the `on_account_created` callback, for deployments that need more than
+    /// `ON_CREATE_ACCOUNT_CALLBACK_GAS` to resolve.
+    pub callback_gas: Option<Gas>,
+    /// Limited access keys with their method names given as a list. Added along with `limited_access_keys`.
+    pub limited_access_keys_v2: Option<Vec<LimitedAccessKeyV2>>,
+    /// Validator key and yoctoNEAR$ amount the new account stakes right after it is created.
+    pub stake: Option<(PublicKey, U128)>,
+    /// Labels recorded on the contract for keys added to the new account, readable with `get_key_label`.
+    pub key_labels: Option<Vec<(PublicKey, String)>>,
+}
+
+impl CreateAccountOptions {
+    /// Code to deploy to the new account, given either as bytes or as a base64 string.
+    pub fn contract_bytes(&self) -> Option<&[u8]> {
+        self.contract_bytes.as_deref()
+            .or_else(|| self.contract_bytes_base64.as_ref().map(|bytes| bytes.0.as_slice()))
+    }
+
+    /// Number of access keys added to the new account.
+    pub fn key_count(&self) -> usize {
+        self.full_access_keys.as_ref().map_or(0, Vec::len)
+            + self.limited_access_keys.as_ref().map_or(0, Vec::len)
+            + self.limited_access_keys_v2.as_ref().map_or(0, Vec::len)
+    }
+}
+
+/// Likely failure reason of a `create_account_advanced` call, as reported by `preview_create_account_advanced`.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PreviewWarning {
+    /// The contract cannot create this account name.
+    CannotCreateAccount,
+    /// The account name matches a reserved pattern.
+    ReservedName,
+    /// The options are rejected, with the message `create_account_advanced` would panic with.
+    InvalidOptions(String),
+    /// The deposit does not cover the storage of the new account plus its stake, if any.
+    InsufficientDeposit { required: U128 },
 }
@@ -0,0 +1,380 @@
+use crate::*;
+
+/// Gas attached to `nft_transfer` when delivering the NFT of a claimed drop.
+pub const GAS_FOR_NFT_TRANSFER: Gas = Gas(15_000_000_000_000);
+
+/// Gas attached to the `on_nft_withdrawn` callback.
+const ON_NFT_WITHDRAWN_CALLBACK_GAS: Gas = Gas(10_000_000_000_000);
+
+/// Sends the NFT held by a claimed drop to the receiver, with the claim memo if any.
+pub(crate) fn transfer_nft(nft: NftDrop, receiver_id: AccountId, memo: Option<String>) -> Promise {
+    ext_nft::ext(nft.contract_id)
+        .with_attached_deposit(1)
+        .with_static_gas(GAS_FOR_NFT_TRANSFER)
+        .nft_transfer(receiver_id, nft.token_id, None, memo)
+}
+
+#[near_bindgen]
+impl LinkDrop {
+    /// Registers the token the funder is about to attach to the drop with `nft_transfer_call` on `contract_id`.
+    /// Anyone can call `nft_on_transfer`, so no other token is accepted for the drop. Registering again
+    /// replaces the expected token. Only callable by the funder of the drop.
+    pub fn expect_nft(&mut self, public_key: PublicKey, contract_id: AccountId, token_id: String) {
+        self.assert_not_paused();
+        let mut drop = self.accounts.get(&public_key).expect("No drop for the given public key");
+        assert_eq!(
+            drop.funder_id.as_ref(),
+            Some(&env::predecessor_account_id()),
+            "Only the funder of the drop can attach an NFT"
+        );
+        assert!(drop.nft.is_none(), "Drop already holds an NFT");
+        // Random drops pay out their pool over several claims, none of which would send the token
+        assert!(drop.random.is_none(), "Random drops cannot hold an NFT");
+        drop.expected_nft = Some(NftDrop { contract_id, token_id });
+        self.accounts.insert(&public_key, &drop);
+    }
+
+    /// NEP-171 receiver: attaches the transferred NFT to the drop whose public key is passed as `msg`.
+    /// The drop must already exist (created with `send`, which pays for its access key), must expect the
+    /// token (see `expect_nft`) and must have been funded by the previous owner of the token. Otherwise
+    /// the call panics and the NFT contract returns the token.
+    pub fn nft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: String,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        self.assert_not_paused();
+        let public_key: PublicKey = msg.parse().expect("msg must be the public key of the drop");
+        let mut drop = self.accounts.get(&public_key).expect("No drop for the given public key");
+        let nft = drop.expected_nft.take().expect("No NFT expected for the drop");
+        assert!(
+            nft.contract_id == env::predecessor_account_id() && nft.token_id == token_id,
+            "Token does not match the one expected for the drop"
+        );
+        assert_eq!(
+            drop.funder_id.as_ref(),
+            Some(&previous_owner_id),
+            "Only the funder of the drop can attach an NFT"
+        );
+        // Only the previous owner matters: an approved sender cannot attach someone else's token,
+        // but it is part of the event so the funder can tell which app made the transfer
+        emit(EventKind::NftAttach(vec![NftAttachLog {
+            funder_id: previous_owner_id,
+            sender_id,
+            contract_id: nft.contract_id.clone(),
+            token_id,
+        }]));
+        drop.nft = Some(nft);
+        self.accounts.insert(&public_key, &drop);
+        PromiseOrValue::Value(false)
+    }
+
+    /// Recovers the NFT of a drop that can no longer be claimed (e.g. its key was lost) by sending it to `to`.
+    /// Once the transfer succeeds, the drop's access key is deleted and its balance goes back to the funder,
+    /// or to `to` if the funder is unknown. If the transfer fails, the drop is restored. Only callable by the owner.
+    pub fn withdraw_nft(&mut self, public_key: PublicKey, to: AccountId) -> Promise {
+        self.assert_owner();
+        let drop = self.internal_remove_drop(&public_key).expect("No drop for the given public key");
+        let nft = drop.nft.clone().expect("Drop does not hold an NFT");
+        transfer_nft(nft, to.clone(), None).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(ON_NFT_WITHDRAWN_CALLBACK_GAS)
+                .on_nft_withdrawn(public_key, drop, to)
+        )
+    }
+
+    /// Callback after `withdraw_nft`. Removes what is left of the drop on success and restores it on failure.
+    pub fn on_nft_withdrawn(
+        &mut self,
+        #[serializer(borsh)] public_key: PublicKey,
+        #[serializer(borsh)] drop: DropInfo,
+        #[serializer(borsh)] to: AccountId,
+    ) -> bool {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        let transfer_succeeded = is_promise_success();
+        if transfer_succeeded {
+            Promise::new(env::current_account_id()).delete_key(public_key);
+            if drop.balance > 0 {
+                Promise::new(drop.funder_id.unwrap_or(to)).transfer(drop.balance);
+            }
+        } else {
+            self.internal_restore_drop(&public_key, drop);
+        }
+        transfer_succeeded
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    fn linkdrop() -> AccountId {
+        "linkdrop".parse().unwrap()
+    }
+
+    fn bob() -> AccountId {
+        "bob".parse().unwrap()
+    }
+
+    fn nft_contract() -> AccountId {
+        "nft".parse().unwrap()
+    }
+
+    /// Creates a contract holding a NEAR drop funded by bob for the given key.
+    fn contract_with_drop(pk: &PublicKey) -> LinkDrop {
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+        contract
+    }
+
+    /// Has bob register `token-1` of the NFT contract for the drop and transfer it.
+    fn attach_nft(contract: &mut LinkDrop, pk: &PublicKey) {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.expect_nft(pk.clone(), nft_contract(), "token-1".to_string());
+
+        // The NFT contract notifies the linkdrop of the transfer from bob
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(nft_contract())
+            .context.clone()
+        );
+        contract.nft_on_transfer(bob(), bob(), "token-1".to_string(), String::from(pk));
+    }
+
+    #[test]
+    fn test_nft_drop_claim_transfers_token() {
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let mut contract = contract_with_drop(&pk);
+
+        attach_nft(&mut contract, &pk);
+
+        // Claim the drop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .signer_account_pk(pk)
+            .account_balance(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.claim("alice".parse().unwrap(), None, Some("order #42".to_string()));
+
+        // The NFT is sent to the claimer
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == nft_contract()).unwrap();
+        match &receipt.actions[0] {
+            VmAction::FunctionCall { function_name, args, deposit, .. } => {
+                assert_eq!(function_name, "nft_transfer");
+                assert_eq!(*deposit, 1);
+                let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(args).unwrap();
+                assert_eq!(args["receiver_id"], "alice");
+                assert_eq!(args["token_id"], "token-1");
+                assert_eq!(args["memo"], "order #42");
+            }
+            action => panic!("Unexpected action {:?}", action),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Random drops cannot hold an NFT")]
+    fn test_nft_on_transfer_rejects_random_drop() {
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let mut contract = LinkDrop::new(linkdrop(), None);
+
+        // Bob creates a random drop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 11)
+            .context.clone()
+        );
+        contract.send_random(pk.clone(), U128(ACCESS_KEY_ALLOWANCE), U128(ACCESS_KEY_ALLOWANCE), 10);
+
+        // Bob cannot expect an NFT for it
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.expect_nft(pk, nft_contract(), "token-1".to_string());
+    }
+
+    #[test]
+    fn test_nft_drop_needs_more_claim_gas() {
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        let mut contract = contract_with_drop(&pk);
+        contract.send(pk2.clone());
+
+        // Attach an NFT to the first drop only
+        attach_nft(&mut contract, &pk);
+
+        // The NFT drop needs the extra gas of the NFT transfer
+        let nft_gas = contract.get_key_information(pk).unwrap().estimated_claim_gas;
+        let near_gas = contract.get_key_information(pk2).unwrap().estimated_claim_gas;
+        assert_eq!(nft_gas, near_gas + GAS_FOR_NFT_TRANSFER);
+    }
+
+    #[test]
+    fn test_withdraw_nft() {
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let mut contract = contract_with_drop(&pk);
+
+        attach_nft(&mut contract, &pk);
+
+        // The owner recovers the NFT
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(linkdrop())
+            .context.clone()
+        );
+        contract.withdraw_nft(pk.clone(), "rescue".parse().unwrap());
+
+        // The drop can no longer be claimed while the transfer is in flight
+        assert!(contract.get_key_information(pk).is_err());
+        assert_eq!(contract.get_total_locked().0, 0);
+
+        // The NFT is sent to the chosen account and the outcome is handled by the callback
+        let receipts = get_created_receipts();
+        let function_names: Vec<_> = receipts
+            .iter()
+            .flat_map(|receipt| receipt.actions.iter())
+            .filter_map(|action| match action {
+                VmAction::FunctionCall { function_name, .. } => Some(function_name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(function_names, vec!["nft_transfer", "on_nft_withdrawn"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this method")]
+    fn test_withdraw_nft_by_non_owner() {
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let mut contract = contract_with_drop(&pk);
+
+        // bob is the funder but not the owner
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.withdraw_nft(pk, bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the funder of the drop can attach an NFT")]
+    fn test_nft_on_transfer_from_other_owner() {
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let mut contract = contract_with_drop(&pk);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.expect_nft(pk.clone(), nft_contract(), "token-1".to_string());
+
+        // Someone other than the funder tries to attach the expected NFT
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(nft_contract())
+            .context.clone()
+        );
+        contract.nft_on_transfer(linkdrop(), linkdrop(), "token-1".to_string(), String::from(&pk));
+    }
+
+    #[test]
+    #[should_panic(expected = "No NFT expected for the drop")]
+    fn test_nft_on_transfer_without_expected_nft() {
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let mut contract = contract_with_drop(&pk);
+
+        // Any account can call the receiver, claiming bob sent it a token
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(nft_contract())
+            .context.clone()
+        );
+        contract.nft_on_transfer(bob(), bob(), "token-1".to_string(), String::from(&pk));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token does not match the one expected for the drop")]
+    fn test_nft_on_transfer_from_other_contract() {
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let mut contract = contract_with_drop(&pk);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.expect_nft(pk.clone(), nft_contract(), "token-1".to_string());
+
+        // Another contract fakes the transfer of the expected token
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id("fake-nft".parse().unwrap())
+            .context.clone()
+        );
+        contract.nft_on_transfer(bob(), bob(), "token-1".to_string(), String::from(&pk));
+    }
+}
@@ -0,0 +1,337 @@
+use crate::*;
+
+#[near_bindgen]
+impl LinkDrop {
+    /// NEP-145: adds the attached deposit to the storage balance of `account_id`, or of the caller.
+    /// Drops created by an account holding a storage balance have their storage paid from it.
+    /// There is no minimum balance, so with `registration_only` the account is registered and the whole
+    /// deposit is refunded. Only the account itself can register, others can add to an existing balance.
+    #[payable]
+    pub fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let deposit = env::attached_deposit();
+        let mut balance = self.storage_balances.get(&account_id).unwrap_or_else(|| {
+            // Registering makes the account pay for the storage of its drops, which it has to opt into
+            assert_eq!(account_id, env::predecessor_account_id(), "Only the account itself can register");
+            StorageBalance {
+                total: U128(0),
+                available: U128(0),
+            }
+        });
+        if registration_only.unwrap_or(false) {
+            if deposit > 0 {
+                Promise::new(env::predecessor_account_id()).transfer(deposit);
+            }
+        } else {
+            balance.total = (balance.total.0 + deposit).into();
+            balance.available = (balance.available.0 + deposit).into();
+            self.storage_available_total = self
+                .storage_available_total
+                .checked_add(deposit)
+                .expect("Total storage balance overflow");
+        }
+        self.storage_balances.insert(&account_id, &balance);
+        balance
+    }
+
+    /// NEP-145: sends `amount`, or all of it, of the caller's storage balance not used by drops back to them.
+    /// Requires exactly 1 yoctoNEAR attached.
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_eq!(env::attached_deposit(), 1, "Requires attached deposit of exactly 1 yoctoNEAR");
+        let account_id = env::predecessor_account_id();
+        let mut balance = self
+            .storage_balances
+            .get(&account_id)
+            .expect("The account is not registered");
+        let amount = amount.map_or(balance.available.0, |amount| amount.0);
+        assert!(amount <= balance.available.0, "Amount exceeds the available storage balance");
+        balance.total = (balance.total.0 - amount).into();
+        balance.available = (balance.available.0 - amount).into();
+        self.storage_available_total = self
+            .storage_available_total
+            .checked_sub(amount)
+            .expect("Total storage balance underflow");
+        self.storage_balances.insert(&account_id, &balance);
+        if amount > 0 {
+            Promise::new(account_id).transfer(amount);
+        }
+        balance
+    }
+
+    /// NEP-145: returns the storage balance of the account, or `None` if it is not registered.
+    pub fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_balances.get(&account_id)
+    }
+}
+
+impl LinkDrop {
+    /// Pays the storage the drop of `public_key` took since `initial_storage` from the storage balance of
+    /// `account_id`, if it has one, and records the charge on the drop to give it back once the drop is removed.
+    /// Accounts without a storage balance leave the storage to the contract, as before NEP-145 was supported,
+    /// and so do top-ups of a drop whose storage another account paid for.
+    pub(crate) fn charge_storage(&mut self, public_key: &PublicKey, account_id: &AccountId, initial_storage: u64) {
+        if self.storage_balances.get(account_id).is_none() {
+            return;
+        }
+        let mut drop = self.accounts.get(public_key).expect("Unexpected public key");
+        let charged = match drop.storage_charge.as_ref() {
+            Some((payer, _)) if payer != account_id => return,
+            Some((_, charged)) => *charged,
+            None => 0,
+        };
+        // Record the charge before measuring the storage, so that the record pays for itself
+        drop.storage_charge = Some((account_id.clone(), charged));
+        self.accounts.insert(public_key, &drop);
+        let cost = Balance::from(env::storage_usage().saturating_sub(initial_storage)) * env::storage_byte_cost();
+        assert!(self.use_storage_balance(account_id, cost), "Insufficient storage balance");
+        drop.storage_charge = Some((account_id.clone(), charged + cost));
+        self.accounts.insert(public_key, &drop);
+    }
+
+    /// Takes `amount` out of the available storage balance of `account_id`. Returns false, taking nothing,
+    /// if the account is not registered or has less available.
+    pub(crate) fn use_storage_balance(&mut self, account_id: &AccountId, amount: Balance) -> bool {
+        match self.storage_balances.get(account_id) {
+            Some(mut balance) if balance.available.0 >= amount => {
+                balance.available = (balance.available.0 - amount).into();
+                self.storage_available_total = self
+                    .storage_available_total
+                    .checked_sub(amount)
+                    .expect("Total storage balance underflow");
+                self.storage_balances.insert(account_id, &balance);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Gives `amount` charged for the storage of a removed drop back to the available storage balance of `account_id`.
+    pub(crate) fn release_storage_balance(&mut self, account_id: &AccountId, amount: Balance) {
+        if let Some(mut balance) = self.storage_balances.get(account_id) {
+            balance.available = (balance.available.0 + amount).into();
+            self.storage_available_total = self
+                .storage_available_total
+                .checked_add(amount)
+                .expect("Total storage balance overflow");
+            self.storage_balances.insert(account_id, &balance);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    fn linkdrop() -> AccountId {
+        "linkdrop".parse().unwrap()
+    }
+
+    fn bob() -> AccountId {
+        "bob".parse().unwrap()
+    }
+
+    /// Creates a contract where bob deposited `amount` for storage.
+    fn contract_with_storage_deposit(amount: Balance) -> LinkDrop {
+        let mut contract = LinkDrop::new(linkdrop(), None);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(amount)
+            .context.clone()
+        );
+        contract.storage_deposit(None, None);
+        contract
+    }
+
+    #[test]
+    fn test_storage_deposit() {
+        let deposit = ACCESS_KEY_ALLOWANCE / 10;
+        let contract = contract_with_storage_deposit(deposit);
+
+        // The whole deposit is available
+        let balance = contract.storage_balance_of(bob()).unwrap();
+        assert_eq!(balance.total.0, deposit);
+        assert_eq!(balance.available.0, deposit);
+        assert!(contract.storage_balance_of(linkdrop()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the account itself can register")]
+    fn test_storage_deposit_registers_other_account() {
+        let mut contract = LinkDrop::new(linkdrop(), None);
+
+        // Alice cannot make bob pay for the storage of his drops
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id("alice".parse().unwrap())
+            .context.clone()
+        );
+        contract.storage_deposit(Some(bob()), Some(true));
+    }
+
+    #[test]
+    fn test_send_charges_storage_balance() {
+        let deposit = ACCESS_KEY_ALLOWANCE / 10;
+        let mut contract = contract_with_storage_deposit(deposit);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Bob creates a drop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        let initial_storage = env::storage_usage();
+        contract.send(pk);
+
+        // The storage of the drop is paid from bob's storage balance
+        let cost = Balance::from(env::storage_usage() - initial_storage) * env::storage_byte_cost();
+        assert!(cost > 0);
+        let balance = contract.storage_balance_of(bob()).unwrap();
+        assert_eq!(balance.total.0, deposit);
+        assert_eq!(balance.available.0, deposit - cost);
+    }
+
+    #[test]
+    fn test_cancel_gives_storage_charge_back() {
+        let deposit = ACCESS_KEY_ALLOWANCE / 10;
+        let mut contract = contract_with_storage_deposit(deposit);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Bob creates a drop and cancels it
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send(pk.clone());
+        assert!(contract.storage_balance_of(bob()).unwrap().available.0 < deposit);
+        assert_eq!(contract.cancel_many(vec![pk]), 1);
+
+        // The storage paid for the drop is available again, not left for the owner to sweep
+        let balance = contract.storage_balance_of(bob()).unwrap();
+        assert_eq!(balance.available.0, deposit);
+        assert_eq!(contract.storage_available_total, deposit);
+    }
+
+    #[test]
+    fn test_send_advanced_and_batch_charge_storage_balance() {
+        let deposit = ACCESS_KEY_ALLOWANCE / 10;
+        let mut contract = contract_with_storage_deposit(deposit);
+        // Create the public keys to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let pk2: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+
+        // Bob creates a drop with send_advanced
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send_advanced(pk, ACCESS_KEY_ALLOWANCE.into(), ACCESS_KEY_ALLOWANCE.into(), "claim".to_string());
+        let after_advanced = contract.storage_balance_of(bob()).unwrap().available.0;
+        assert!(after_advanced < deposit);
+
+        // Then one with send_batch, attaching nothing for its storage
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send_batch(vec![(pk2, ACCESS_KEY_ALLOWANCE.into())]);
+
+        // Both drops are paid from bob's storage balance
+        assert!(contract.storage_balance_of(bob()).unwrap().available.0 < after_advanced);
+        assert_eq!(contract.storage_available_total, contract.storage_balance_of(bob()).unwrap().available.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient storage balance")]
+    fn test_send_with_insufficient_storage_balance() {
+        let mut contract = contract_with_storage_deposit(1);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // One yoctoNEAR does not cover the storage of a drop
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send(pk);
+    }
+
+    #[test]
+    fn test_storage_withdraw_remainder() {
+        let deposit = ACCESS_KEY_ALLOWANCE / 10;
+        let mut contract = contract_with_storage_deposit(deposit);
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Bob creates a drop, which uses part of the storage balance
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE * 2)
+            .context.clone()
+        );
+        contract.send(pk);
+        let remainder = contract.storage_balance_of(bob()).unwrap().available.0;
+
+        // Bob withdraws the rest
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(linkdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(1)
+            .account_balance(deposit)
+            .context.clone()
+        );
+        let balance = contract.storage_withdraw(None);
+
+        // Only the part used by the drop is left and the remainder is sent back
+        assert_eq!(balance.available.0, 0);
+        assert_eq!(balance.total.0, deposit - remainder);
+        assert_eq!(contract.storage_available_total, 0);
+        let receipts = get_created_receipts();
+        let receipt = receipts.iter().find(|receipt| receipt.receiver_id == bob()).unwrap();
+        assert_eq!(receipt.actions, vec![VmAction::Transfer { deposit: remainder }]);
+    }
+}